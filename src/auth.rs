@@ -0,0 +1,150 @@
+use crate::models::SurrDB;
+use anyhow::Result;
+use base64::prelude::*;
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds before expiry at which a token is considered stale
+const EXPIRY_MARGIN: u64 = 30;
+
+impl SurrDB {
+    /// Build the signin payload for the configured auth level
+    pub fn signin_params(&self) -> Value {
+        match self.auth.as_str() {
+            "ns" => json!({ "ns": self.namespace, "user": self.user, "pass": self.pass }),
+            "db" => json!({
+                "ns": self.namespace,
+                "db": self.database,
+                "user": self.user,
+                "pass": self.pass,
+            }),
+            "record" => json!({
+                "ns": self.namespace,
+                "db": self.database,
+                "ac": self.access,
+                "sc": self.access,
+                "user": self.user,
+                "pass": self.pass,
+            }),
+            _ => json!({ "user": self.user, "pass": self.pass }),
+        }
+    }
+
+    /// Sign in through `/signin` and keep the returned JWT
+    pub fn signin(&self) -> Result<(String, u16)> {
+        let url = format!("{}://{}/signin", self.schema, self.authority()?);
+        let params = self.signin_params();
+
+        let response = self.send(|client| {
+            client
+                .post(&url)
+                .header("Accept", "application/json")
+                .json(&params)
+        })?;
+
+        let status = response.status().as_u16();
+        let body = response.text()?;
+
+        let token = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|v| v["token"].as_str().map(str::to_string));
+        *self.token.borrow_mut() = if status == 200 { token } else { None };
+
+        Ok((body, status))
+    }
+
+    /// Return a valid bearer token, signing in again if it is missing or expired.
+    /// On failure the `/signin` response is returned instead.
+    pub fn bearer(&self) -> Result<std::result::Result<String, (String, u16)>> {
+        if let Some(token) = self.token.borrow().as_ref()
+            && !token_expired(token)
+        {
+            return Ok(Ok(token.clone()));
+        }
+
+        let (body, status) = self.signin()?;
+        match self.token.borrow().as_ref() {
+            Some(token) => Ok(Ok(token.clone())),
+            None => Ok(Err((body, status))),
+        }
+    }
+
+    /// Forget the current token so the next request signs in again
+    pub fn reset_token(&self) {
+        self.token.borrow_mut().take();
+    }
+
+    /// Forget the token, connections and cached schema after connection settings change
+    pub fn reset_session(&self) {
+        self.reset_token();
+        self.reset_rpc();
+        self.reset_http();
+        self.invalidate_schema();
+    }
+}
+
+/// Check the `exp` claim of a JWT against the current time
+fn token_expired(token: &str) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    token_expired_at(token, now)
+}
+
+/// Check the `exp` claim of a JWT against `now`, in seconds since the epoch
+fn token_expired_at(token: &str, now: u64) -> bool {
+    let Some(claims) = token.split('.').nth(1) else {
+        return true;
+    };
+    let Ok(bytes) = BASE64_URL_SAFE_NO_PAD.decode(claims.trim_end_matches('=')) else {
+        return true;
+    };
+    let Ok(claims) = serde_json::from_slice::<Value>(&bytes) else {
+        return true;
+    };
+
+    match claims["exp"].as_u64() {
+        Some(exp) => now + EXPIRY_MARGIN >= exp,
+        // Tokens without expiry stay valid until the server rejects them
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn token(claims: Value) -> String {
+        format!("eyJhbGciOiJIUzI1NiJ9.{}.sig", BASE64_URL_SAFE_NO_PAD.encode(claims.to_string()))
+    }
+
+    #[test]
+    fn tokens_expire_thirty_seconds_early() {
+        assert!(!token_expired_at(&token(json!({ "exp": NOW + 3600 })), NOW));
+        assert!(!token_expired_at(&token(json!({ "exp": NOW + EXPIRY_MARGIN + 1 })), NOW));
+        assert!(token_expired_at(&token(json!({ "exp": NOW + EXPIRY_MARGIN })), NOW));
+        assert!(token_expired_at(&token(json!({ "exp": NOW - 1 })), NOW));
+    }
+
+    #[test]
+    fn tokens_without_expiry_stay_valid() {
+        assert!(!token_expired_at(&token(json!({ "ID": "root" })), NOW));
+    }
+
+    #[test]
+    fn unreadable_tokens_count_as_expired() {
+        assert!(token_expired_at("opaque", NOW));
+        assert!(token_expired_at("a.!!!.c", NOW));
+        assert!(token_expired_at(&format!("a.{}.c", BASE64_URL_SAFE_NO_PAD.encode("not json")), NOW));
+    }
+
+    #[test]
+    fn padded_claims_are_accepted() {
+        let claims = BASE64_URL_SAFE.encode(json!({ "exp": NOW - 1, "id": 1 }).to_string());
+        assert!(claims.ends_with('='));
+        assert!(token_expired_at(&format!("a.{}.c", claims), NOW));
+    }
+}
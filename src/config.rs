@@ -0,0 +1,353 @@
+use crate::dsn::Dsn;
+use crate::models::SurrDB;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Environment variable pointing at another config file
+pub const CONFIG_ENV: &str = "SURRCLI_CONFIG";
+
+/// Environment variable naming the `[settings.<name>]` table to use
+pub const SETTINGS_ENV: &str = "SURRCLI_SETTINGS";
+
+/// Environment variables read as connection settings
+const ENV_VARS: &[(&str, &str)] = &[
+    ("SURRCLI_HOST", "host"),
+    ("SURRCLI_USER", "user"),
+    ("SURRCLI_PASS", "pass"),
+    ("SURRCLI_NS", "namespace"),
+    ("SURRCLI_DB", "database"),
+    ("SURRCLI_PROFILE", "profile"),
+];
+
+/// Settings that can come from the config file, the environment or flags
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub host: Option<String>,
+    pub user: Option<String>,
+    /// Never read from the config file
+    #[serde(skip)]
+    pub pass: Option<String>,
+    pub namespace: Option<String>,
+    pub database: Option<String>,
+    pub schema: Option<String>,
+    pub transport: Option<String>,
+    pub auth: Option<String>,
+    pub access: Option<String>,
+    pub timeout: Option<u64>,
+    pub connect_timeout: Option<u64>,
+    pub retries: Option<u32>,
+    pub retry_delay: Option<u64>,
+    pub retry_unavailable: Option<bool>,
+    pub format: Option<String>,
+    pub profile: Option<String>,
+    pub ca_cert: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    pub server_name: Option<String>,
+    pub insecure: Option<bool>,
+    pub proxy: Option<String>,
+    pub ssh: Option<String>,
+}
+
+/// `config.toml`: defaults plus named settings laid over them
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    #[serde(default)]
+    pub defaults: Settings,
+    #[serde(default)]
+    pub settings: BTreeMap<String, Settings>,
+}
+
+impl Settings {
+    /// Take the values `other` sets, keeping the rest
+    pub fn overlay(&mut self, other: &Settings) {
+        let fields = [
+            (&other.host, &mut self.host),
+            (&other.user, &mut self.user),
+            (&other.pass, &mut self.pass),
+            (&other.namespace, &mut self.namespace),
+            (&other.database, &mut self.database),
+            (&other.schema, &mut self.schema),
+            (&other.transport, &mut self.transport),
+            (&other.auth, &mut self.auth),
+            (&other.access, &mut self.access),
+            (&other.format, &mut self.format),
+            (&other.profile, &mut self.profile),
+            (&other.ca_cert, &mut self.ca_cert),
+            (&other.client_cert, &mut self.client_cert),
+            (&other.client_key, &mut self.client_key),
+            (&other.server_name, &mut self.server_name),
+            (&other.proxy, &mut self.proxy),
+            (&other.ssh, &mut self.ssh),
+        ];
+        for (value, field) in fields {
+            if value.is_some() {
+                *field = value.clone();
+            }
+        }
+        for (value, field) in [
+            (other.timeout, &mut self.timeout),
+            (other.connect_timeout, &mut self.connect_timeout),
+            (other.retry_delay, &mut self.retry_delay),
+        ] {
+            if value.is_some() {
+                *field = value;
+            }
+        }
+        if other.retries.is_some() {
+            self.retries = other.retries;
+        }
+        if other.retry_unavailable.is_some() {
+            self.retry_unavailable = other.retry_unavailable;
+        }
+        if other.insecure.is_some() {
+            self.insecure = other.insecure;
+        }
+    }
+
+    /// Check values that have a fixed set of choices
+    fn validate(&self) -> Result<()> {
+        let choices: [(&str, &Option<String>, &[&str]); 4] = [
+            ("schema", &self.schema, &["http", "https"]),
+            ("transport", &self.transport, &["http", "ws"]),
+            ("auth", &self.auth, &["root", "ns", "db", "record"]),
+            ("format", &self.format, crate::render::FORMATS),
+        ];
+        if let Some(proxy) = &self.proxy {
+            crate::proxy::validate(proxy)?;
+        }
+        if let Some(ssh) = &self.ssh {
+            crate::tunnel::parse_bastion(ssh)?;
+        }
+        for (name, value, allowed) in choices {
+            if let Some(value) = value
+                && !allowed.contains(&value.as_str())
+            {
+                return Err(anyhow::anyhow!(
+                    "Invalid {} {}. Must be one of {}.",
+                    name,
+                    value,
+                    allowed.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Connection settings from the SURRCLI_* environment variables
+    pub fn from_env() -> Settings {
+        let mut settings = Settings::default();
+        for (var, name) in ENV_VARS {
+            let Some(value) = std::env::var(var).ok().filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            let field = match *name {
+                "host" => &mut settings.host,
+                "user" => &mut settings.user,
+                "pass" => &mut settings.pass,
+                "namespace" => &mut settings.namespace,
+                "database" => &mut settings.database,
+                _ => &mut settings.profile,
+            };
+            *field = Some(value);
+        }
+        settings
+    }
+
+    /// Check whether any setting that picks a server is given
+    pub fn has_connection(&self) -> bool {
+        self.host.is_some()
+            || self.user.is_some()
+            || self.namespace.is_some()
+            || self.database.is_some()
+            || self.schema.is_some()
+            || self.transport.is_some()
+            || self.auth.is_some()
+            || self.access.is_some()
+    }
+}
+
+impl From<&Dsn> for Settings {
+    fn from(dsn: &Dsn) -> Self {
+        Settings {
+            host: Some(dsn.host.clone()),
+            user: dsn.user.clone(),
+            pass: dsn.pass.clone(),
+            namespace: dsn.namespace.clone(),
+            database: dsn.database.clone(),
+            schema: Some(dsn.schema.clone()),
+            transport: Some(dsn.transport.clone()),
+            auth: dsn.auth.clone(),
+            access: dsn.access.clone(),
+            timeout: dsn.timeout,
+            ..Default::default()
+        }
+    }
+}
+
+/// `$SURRCLI_CONFIG`, or `config.toml` under `$XDG_CONFIG_HOME/surrcli`
+/// (`~/.config/surrcli` when unset)
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os(CONFIG_ENV).filter(|p| !p.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(base.join("surrcli").join("config.toml"))
+}
+
+/// Settings from the config file, with the named settings laid over the defaults.
+/// A missing file gives no settings.
+pub fn load_config(name: Option<&str>) -> Result<Settings> {
+    let Some(path) = config_path() else {
+        return Ok(Settings::default());
+    };
+    if !path.exists() {
+        if let Some(name) = name {
+            return Err(anyhow::anyhow!("No config file {} for settings {}", path.display(), name));
+        }
+        return Ok(Settings::default());
+    }
+
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+    let file: ConfigFile =
+        toml::from_str(&text).map_err(|e| anyhow::anyhow!("Invalid config {}: {}", path.display(), e))?;
+
+    let mut settings = file.defaults;
+    if let Some(name) = name {
+        let named = file.settings.get(name).ok_or_else(|| {
+            let names: Vec<&str> = file.settings.keys().map(String::as_str).collect();
+            anyhow::anyhow!("No settings named {} in {}. Available: {}", name, path.display(), names.join(", "))
+        })?;
+        settings.overlay(named);
+    }
+    settings
+        .validate()
+        .map_err(|e| anyhow::anyhow!("{} in {}", e, path.display()))?;
+    Ok(settings)
+}
+
+impl SurrDB {
+    /// Take the values a layer sets and remember where they came from
+    pub fn apply_settings(&mut self, settings: &Settings, source: &str) {
+        let fields = [
+            ("host", &settings.host, &mut self.host),
+            ("user", &settings.user, &mut self.user),
+            ("pass", &settings.pass, &mut self.pass),
+            ("namespace", &settings.namespace, &mut self.namespace),
+            ("database", &settings.database, &mut self.database),
+            ("schema", &settings.schema, &mut self.schema),
+            ("transport", &settings.transport, &mut self.transport),
+            ("auth", &settings.auth, &mut self.auth),
+            ("access", &settings.access, &mut self.access),
+            ("format", &settings.format, &mut self.format),
+            ("ca_cert", &settings.ca_cert, &mut self.tls.ca_cert),
+            ("client_cert", &settings.client_cert, &mut self.tls.client_cert),
+            ("client_key", &settings.client_key, &mut self.tls.client_key),
+            ("server_name", &settings.server_name, &mut self.tls.server_name),
+            ("proxy", &settings.proxy, &mut self.proxy),
+            ("ssh", &settings.ssh, &mut self.ssh),
+        ];
+        for (name, value, field) in fields {
+            if let Some(value) = value {
+                *field = value.clone();
+                self.sources.insert(name.to_string(), source.to_string());
+            }
+        }
+        let numbers = [
+            ("timeout", settings.timeout, &mut self.timeout),
+            ("connect_timeout", settings.connect_timeout, &mut self.connect_timeout),
+            ("retry_delay", settings.retry_delay, &mut self.retry_delay),
+        ];
+        for (name, value, field) in numbers {
+            if let Some(value) = value {
+                *field = value;
+                self.sources.insert(name.to_string(), source.to_string());
+            }
+        }
+        if let Some(retries) = settings.retries {
+            self.retries = retries;
+            self.sources.insert("retries".to_string(), source.to_string());
+        }
+        if let Some(retry_unavailable) = settings.retry_unavailable {
+            self.retry_unavailable = retry_unavailable;
+            self.sources.insert("retry_unavailable".to_string(), source.to_string());
+        }
+        if let Some(insecure) = settings.insecure {
+            self.tls.insecure = insecure;
+            self.sources.insert("insecure".to_string(), source.to_string());
+        }
+    }
+
+    /// Record where a set of options came from
+    pub fn set_source(&mut self, names: &[&str], source: &str) {
+        for name in names {
+            self.sources.insert(name.to_string(), source.to_string());
+        }
+    }
+
+    /// Where an option's value came from, built-in when never set
+    pub fn source(&self, name: &str) -> &str {
+        self.sources.get(name).map_or("built-in", String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(host: &str, retries: Option<u32>, retry_unavailable: Option<bool>) -> Settings {
+        Settings {
+            host: Some(host.to_string()),
+            retries,
+            retry_unavailable,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn later_layers_win_and_record_their_source() {
+        let mut surr = SurrDB::default();
+        assert_eq!(surr.source("retry_unavailable"), "built-in");
+
+        surr.apply_settings(&layer("config:8000", Some(5), Some(true)), "config");
+        assert_eq!((surr.host.as_str(), surr.source("host")), ("config:8000", "config"));
+        assert_eq!((surr.retries, surr.source("retries")), (5, "config"));
+        assert_eq!((surr.retry_unavailable, surr.source("retry_unavailable")), (true, "config"));
+
+        surr.apply_settings(&layer("profile:8000", None, Some(false)), "profile dev");
+        assert_eq!((surr.host.as_str(), surr.source("host")), ("profile:8000", "profile dev"));
+        assert_eq!((surr.retries, surr.source("retries")), (5, "config"));
+        assert_eq!((surr.retry_unavailable, surr.source("retry_unavailable")), (false, "profile dev"));
+
+        surr.apply_settings(&layer("env:8000", Some(1), None), "env");
+        assert_eq!((surr.host.as_str(), surr.source("host")), ("env:8000", "env"));
+        assert_eq!((surr.retries, surr.source("retries")), (1, "env"));
+        assert_eq!((surr.retry_unavailable, surr.source("retry_unavailable")), (false, "profile dev"));
+
+        surr.apply_settings(&layer("flag:8000", None, Some(true)), "flag");
+        assert_eq!((surr.host.as_str(), surr.source("host")), ("flag:8000", "flag"));
+        assert_eq!((surr.retries, surr.source("retries")), (1, "env"));
+        assert_eq!((surr.retry_unavailable, surr.source("retry_unavailable")), (true, "flag"));
+    }
+
+    #[test]
+    fn named_settings_overlay_the_defaults() {
+        let file: ConfigFile = toml::from_str(
+            "[defaults]\nhost = \"db:8000\"\nretries = 3\n\n[settings.ci]\nretry_unavailable = true\n",
+        )
+        .unwrap();
+        let mut settings = file.defaults;
+        settings.overlay(&file.settings["ci"]);
+        assert_eq!(settings.host.as_deref(), Some("db:8000"));
+        assert_eq!(settings.retries, Some(3));
+        assert_eq!(settings.retry_unavailable, Some(true));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::models::SurrDB;
+use crate::models::{LogEntry, Profile, SavedQuery, SurrDB, TlsOptions, VaultEntry, VaultKey};
 use anyhow::Result;
 use rusqlite::Connection;
 use std::path::PathBuf;
@@ -53,6 +53,39 @@ fn db_table_set(conn: &Connection) -> Result<()> {
         )",
         [],
     )?;
+    db_add_column(conn, "Profile", "Transport", "TEXT NOT NULL DEFAULT 'http'")?;
+    db_add_column(conn, "Profile", "Auth", "TEXT NOT NULL DEFAULT 'root'")?;
+    db_add_column(conn, "Profile", "Access", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Profile", "Vault", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Profile", "IsDefault", "INTEGER NOT NULL DEFAULT 0")?;
+    db_add_column(conn, "Profile", "CaCert", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Profile", "ClientCert", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Profile", "ClientKey", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Profile", "ServerName", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Profile", "Insecure", "INTEGER NOT NULL DEFAULT 0")?;
+    db_add_column(conn, "Profile", "Proxy", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Profile", "Ssh", "TEXT NOT NULL DEFAULT ''")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS VaultKey (
+            kid INTEGER PRIMARY KEY CHECK (kid = 1),
+            Salt BLOB NOT NULL,
+            Nonce BLOB NOT NULL,
+            Verifier BLOB NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS Vault (
+            vid INTEGER PRIMARY KEY AUTOINCREMENT,
+            Idx TEXT NOT NULL UNIQUE,
+            Nonce BLOB NOT NULL,
+            Secret BLOB NOT NULL,
+            Date TEXT NOT NULL
+        )",
+        [],
+    )?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS Sess (
@@ -60,6 +93,14 @@ fn db_table_set(conn: &Connection) -> Result<()> {
         )",
         [],
     )?;
+    db_add_column(conn, "Sess", "Profile", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Sess", "Host", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Sess", "NS", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Sess", "DB", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Sess", "Query", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Sess", "Date", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "Sess", "Duration", "INTEGER NOT NULL DEFAULT 0")?;
+    db_add_column(conn, "Sess", "Status", "TEXT NOT NULL DEFAULT ''")?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS SQuery (
@@ -69,6 +110,13 @@ fn db_table_set(conn: &Connection) -> Result<()> {
         )",
         [],
     )?;
+    db_add_column(conn, "SQuery", "Params", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "SQuery", "Description", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "SQuery", "Tags", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "SQuery", "Created", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "SQuery", "Updated", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "SQuery", "LastRun", "TEXT NOT NULL DEFAULT ''")?;
+    db_add_column(conn, "SQuery", "Runs", "INTEGER NOT NULL DEFAULT 0")?;
 
     conn.execute(
         "CREATE TABLE IF NOT EXISTS SOut (
@@ -78,6 +126,24 @@ fn db_table_set(conn: &Connection) -> Result<()> {
         )",
         [],
     )?;
+    db_add_column(conn, "SOut", "Sid", "INTEGER NOT NULL DEFAULT 0")?;
+    db_add_column(conn, "SOut", "Output", "TEXT")?;
+    db_add_column(conn, "SOut", "Size", "INTEGER NOT NULL DEFAULT 0")?;
+
+    Ok(())
+}
+
+/// Add a column to an existing table if it is missing
+fn db_add_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<usize, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name.eq_ignore_ascii_case(column));
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+    }
 
     Ok(())
 }
@@ -117,11 +183,16 @@ impl SurrDB {
             return Ok(());
         }
 
-        let conn = get_conn()?;
-        conn.execute(
-            "INSERT INTO Profile (Idx, Host, Sch, DBUser, NS, DB, Date) VALUES (?, ?, ?, ?, ?, ?, datetime('now', 'localtime'))",
-            [name, &self.host, &self.schema, &self.user, &self.namespace, &self.database],
-        )?;
+        // A profile is saved with its TLS and network settings or not at all
+        db_transaction(|| {
+            let conn = get_conn()?;
+            conn.execute(
+                "INSERT INTO Profile (Idx, Host, Sch, DBUser, NS, DB, Transport, Auth, Access, Date) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now', 'localtime'))",
+                [name, &self.host, &self.schema, &self.user, &self.namespace, &self.database, &self.transport, &self.auth, &self.access],
+            )?;
+            db_set_profile_tls(name, &self.tls)?;
+            db_set_profile_network(name, &self.proxy, &self.ssh)
+        })?;
 
         crate::utils::print_suc("Profile saved.");
         Ok(())
@@ -132,14 +203,15 @@ impl SurrDB {
 pub fn db_show_profiles() -> Result<()> {
     let conn = get_conn()?;
     let mut stmt = conn.prepare(
-        "SELECT pid, Idx, Host, Sch, DBUser, NS, DB, Date FROM Profile"
+        "SELECT pid, Idx, Host, Sch, DBUser, NS, DB, Transport, Vault, Date, IsDefault FROM Profile"
     )?;
 
     let mut rows = stmt.query([])?;
+    let mut has_default = false;
 
     let mut table = comfy_table::Table::new();
     table.load_preset(comfy_table::presets::UTF8_FULL);
-    table.set_header(vec!["ID", "NAME", "HOST", "PROTOCOL", "USER", "NAMESPACE", "DATABASE", "CREATION DATE"]);
+    table.set_header(vec!["ID", "NAME", "HOST", "PROTOCOL", "USER", "NAMESPACE", "DATABASE", "TRANSPORT", "PASSWORD", "CREATION DATE"]);
 
     while let Some(row) = rows.next()? {
         let pid: i32 = row.get(0)?;
@@ -149,7 +221,12 @@ pub fn db_show_profiles() -> Result<()> {
         let dbuser: String = row.get(4)?;
         let ns: String = row.get(5)?;
         let db: String = row.get(6)?;
-        let date: String = row.get(7)?;
+        let transport: String = row.get(7)?;
+        let vault: String = row.get(8)?;
+        let date: String = row.get(9)?;
+        let is_default: bool = row.get(10)?;
+        has_default |= is_default;
+        let idx = if is_default { format!("{} *", idx) } else { idx };
 
         table.add_row(vec![
             pid.to_string(),
@@ -159,17 +236,76 @@ pub fn db_show_profiles() -> Result<()> {
             dbuser,
             ns,
             db,
+            transport,
+            if vault.is_empty() { String::new() } else { "stored".to_string() },
             date,
         ]);
     }
 
     println!();
     println!("{}", table);
+    if has_default {
+        println!("* default profile");
+    }
     println!();
 
     Ok(())
 }
 
+/// Profile loaded on startup when no connection flags are given
+pub fn db_default_profile() -> Result<Option<String>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Idx FROM Profile WHERE IsDefault = 1")?;
+
+    let mut rows = stmt.query([])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Run several writes in one transaction, rolling all of them back on error.
+/// The writes must not open a transaction of their own.
+pub fn db_transaction<T>(writes: impl FnOnce() -> Result<T>) -> Result<T> {
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+    let value = writes()?;
+    tx.commit()?;
+    Ok(value)
+}
+
+/// Mark a profile as the default one, or clear the default with `None`
+pub fn db_set_default_profile(name: Option<&str>) -> Result<()> {
+    if let Some(name) = name
+        && !db_valid_index(name)?
+    {
+        return Err(anyhow::anyhow!("Profile {} does not exist.", name));
+    }
+
+    // One statement, so this also works inside db_transaction
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE Profile SET IsDefault = CASE WHEN Idx = ? THEN 1 ELSE 0 END",
+        [name],
+    )?;
+
+    match name {
+        Some(name) => crate::utils::print_suc(&format!("{} is the default profile.", name)),
+        None => crate::utils::print_suc("No default profile."),
+    }
+    Ok(())
+}
+
+/// Names of all saved profiles
+pub fn db_profile_names() -> Result<Vec<String>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Idx FROM Profile ORDER BY Idx")?;
+    let names = stmt
+        .query_map([], |row| row.get::<usize, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(names)
+}
+
 /// Delete a profile by index
 pub fn db_drop_idx(idx: &str) -> Result<()> {
     if !db_valid_index(idx)? {
@@ -178,7 +314,15 @@ pub fn db_drop_idx(idx: &str) -> Result<()> {
     }
 
     let conn = get_conn()?;
+    let vault = db_profile_vault(idx)?;
     conn.execute("DELETE FROM Profile WHERE Idx = ?", [idx])?;
+    // Drop the stored password once no profile references it
+    if let Some(vault) = vault {
+        conn.execute(
+            "DELETE FROM Vault WHERE Idx = ?1 AND NOT EXISTS (SELECT 1 FROM Profile WHERE Vault = ?1)",
+            [&vault],
+        )?;
+    }
     crate::utils::print_suc(&format!("{} deleted.", idx));
     Ok(())
 }
@@ -192,7 +336,7 @@ impl SurrDB {
         }
 
         let conn = get_conn()?;
-        let mut stmt = conn.prepare("SELECT Host, Sch, DBUser, NS, DB FROM Profile WHERE Idx = ?")?;
+        let mut stmt = conn.prepare("SELECT Host, Sch, DBUser, NS, DB, Transport, Auth, Access FROM Profile WHERE Idx = ?")?;
 
         let mut rows = stmt.query([idx])?;
         if let Some(row) = rows.next()? {
@@ -201,12 +345,375 @@ impl SurrDB {
             self.user = row.get::<usize, String>(2)?;
             self.namespace = row.get::<usize, String>(3)?;
             self.database = row.get::<usize, String>(4)?;
+            self.transport = row.get::<usize, String>(5)?;
+            self.auth = row.get::<usize, String>(6)?;
+            self.access = row.get::<usize, String>(7)?;
+            self.profile = idx.to_string();
+            self.tls = db_profile_tls(idx)?;
+            (self.proxy, self.ssh) = db_profile_network(idx)?;
+            self.set_source(
+                &["host", "schema", "user", "namespace", "database", "transport", "auth", "access"],
+                &format!("profile {}", idx),
+            );
+            self.set_source(
+                &["ca_cert", "client_cert", "client_key", "server_name", "insecure", "proxy", "ssh"],
+                &format!("profile {}", idx),
+            );
+        }
+        self.reset_session();
+
+        Ok(())
+    }
+}
+
+/// All saved profiles
+pub fn db_profiles() -> Result<Vec<Profile>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT pid, Idx, Host, Sch, DBUser, NS, DB, Transport, Auth, Access, Vault, IsDefault, Date,
+                CaCert, ClientCert, ClientKey, ServerName, Insecure, Proxy, Ssh FROM Profile ORDER BY pid",
+    )?;
+    let profiles = stmt
+        .query_map([], |row| {
+            Ok(Profile {
+                pid: row.get(0)?,
+                idx: row.get(1)?,
+                host: row.get(2)?,
+                sch: row.get(3)?,
+                dbuser: row.get(4)?,
+                ns: row.get(5)?,
+                db: row.get(6)?,
+                transport: row.get(7)?,
+                auth: row.get(8)?,
+                access: row.get(9)?,
+                vault: row.get(10)?,
+                is_default: row.get(11)?,
+                date: row.get(12)?,
+                tls: TlsOptions {
+                    ca_cert: row.get(13)?,
+                    client_cert: row.get(14)?,
+                    client_key: row.get(15)?,
+                    server_name: row.get(16)?,
+                    insecure: row.get(17)?,
+                },
+                proxy: row.get(18)?,
+                ssh: row.get(19)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(profiles)
+}
+
+/// Insert a profile, or replace the fields of an existing one with the same name
+pub fn db_put_profile(profile: &Profile) -> Result<()> {
+    let conn = get_conn()?;
+    let fields = [
+        &profile.host, &profile.sch, &profile.dbuser, &profile.ns, &profile.db, &profile.transport, &profile.auth, &profile.access, &profile.idx,
+    ];
+    if db_valid_index(&profile.idx)? {
+        conn.execute(
+            "UPDATE Profile SET Host = ?, Sch = ?, DBUser = ?, NS = ?, DB = ?, Transport = ?, Auth = ?, Access = ? WHERE Idx = ?",
+            fields,
+        )?;
+    } else {
+        conn.execute(
+            "INSERT INTO Profile (Host, Sch, DBUser, NS, DB, Transport, Auth, Access, Idx, Date) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now', 'localtime'))",
+            fields,
+        )?;
+    }
+    db_set_profile_tls(&profile.idx, &profile.tls)?;
+    db_set_profile_network(&profile.idx, &profile.proxy, &profile.ssh)
+}
+
+/// TLS settings stored with a profile
+pub fn db_profile_tls(idx: &str) -> Result<TlsOptions> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT CaCert, ClientCert, ClientKey, ServerName, Insecure FROM Profile WHERE Idx = ?",
+    )?;
+    let mut rows = stmt.query([idx])?;
+    match rows.next()? {
+        Some(row) => Ok(TlsOptions {
+            ca_cert: row.get(0)?,
+            client_cert: row.get(1)?,
+            client_key: row.get(2)?,
+            server_name: row.get(3)?,
+            insecure: row.get(4)?,
+        }),
+        None => Err(anyhow::anyhow!("Profile {} does not exist.", idx)),
+    }
+}
+
+/// Store the TLS settings of a profile
+pub fn db_set_profile_tls(idx: &str, tls: &TlsOptions) -> Result<()> {
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE Profile SET CaCert = ?, ClientCert = ?, ClientKey = ?, ServerName = ?, Insecure = ? WHERE Idx = ?",
+        rusqlite::params![tls.ca_cert, tls.client_cert, tls.client_key, tls.server_name, tls.insecure, idx],
+    )?;
+    Ok(())
+}
+
+/// Proxy and SSH bastion stored with a profile
+pub fn db_profile_network(idx: &str) -> Result<(String, String)> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Proxy, Ssh FROM Profile WHERE Idx = ?")?;
+    let mut rows = stmt.query([idx])?;
+    match rows.next()? {
+        Some(row) => Ok((row.get(0)?, row.get(1)?)),
+        None => Err(anyhow::anyhow!("Profile {} does not exist.", idx)),
+    }
+}
+
+/// Store the proxy and SSH bastion of a profile
+pub fn db_set_profile_network(idx: &str, proxy: &str, ssh: &str) -> Result<()> {
+    let conn = get_conn()?;
+    conn.execute("UPDATE Profile SET Proxy = ?, Ssh = ? WHERE Idx = ?", [proxy, ssh, idx])?;
+    Ok(())
+}
+
+/// Profile fields accepted by `.profile set`, with their columns
+pub const PROFILE_FIELDS: &[(&str, &str)] = &[
+    ("host", "Host"),
+    ("schema", "Sch"),
+    ("user", "DBUser"),
+    ("ns", "NS"),
+    ("db", "DB"),
+    ("transport", "Transport"),
+    ("auth", "Auth"),
+    ("access", "Access"),
+    ("ca_cert", "CaCert"),
+    ("client_cert", "ClientCert"),
+    ("client_key", "ClientKey"),
+    ("server_name", "ServerName"),
+    ("insecure", "Insecure"),
+    ("proxy", "Proxy"),
+    ("ssh", "Ssh"),
+];
+
+/// Overwrite a profile with the current session, keeping its id and date
+impl SurrDB {
+    pub fn db_update_profile(&self, name: &str) -> Result<()> {
+        if !db_valid_index(name)? {
+            return Err(anyhow::anyhow!("Profile {} does not exist.", name));
         }
 
+        let conn = get_conn()?;
+        conn.execute(
+            "UPDATE Profile SET Host = ?, Sch = ?, DBUser = ?, NS = ?, DB = ?, Transport = ?, Auth = ?, Access = ? WHERE Idx = ?",
+            [&self.host, &self.schema, &self.user, &self.namespace, &self.database, &self.transport, &self.auth, &self.access, name],
+        )?;
+        db_set_profile_tls(name, &self.tls)?;
+        db_set_profile_network(name, &self.proxy, &self.ssh)?;
+
+        crate::utils::print_suc(&format!("{} updated.", name));
         Ok(())
     }
 }
 
+/// Rename a profile and its stored password
+pub fn db_rename_profile(old: &str, new: &str) -> Result<()> {
+    if !db_valid_index(old)? {
+        return Err(anyhow::anyhow!("Profile {} does not exist.", old));
+    }
+    if db_valid_index(new)? {
+        return Err(anyhow::anyhow!("Profile {} already exists.", new));
+    }
+
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("UPDATE Profile SET Idx = ? WHERE Idx = ?", [new, old])?;
+    // The stored password follows the name only when no other profile uses
+    // it or an entry of the new name; otherwise the reference stays as it is
+    if let Some(vault) = db_profile_vault(new)?
+        && vault != new
+    {
+        let others: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM Profile WHERE Idx != ?1 AND Vault IN (?2, ?1)",
+            [new, &vault],
+            |row| row.get(0),
+        )?;
+        if others == 0 {
+            tx.execute("DELETE FROM Vault WHERE Idx = ?", [new])?;
+            tx.execute("UPDATE Vault SET Idx = ? WHERE Idx = ?", [new, &vault])?;
+            tx.execute("UPDATE Profile SET Vault = ? WHERE Idx = ?", [new, new])?;
+        }
+    }
+    tx.commit()?;
+
+    crate::utils::print_suc(&format!("{} renamed to {}.", old, new));
+    Ok(())
+}
+
+/// Copy a profile, and its stored password, under a new name
+pub fn db_clone_profile(src: &str, dst: &str) -> Result<()> {
+    if !db_valid_index(src)? {
+        return Err(anyhow::anyhow!("Profile {} does not exist.", src));
+    }
+    if db_valid_index(dst)? {
+        return Err(anyhow::anyhow!("Profile {} already exists.", dst));
+    }
+
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO Profile (Idx, Host, Sch, DBUser, NS, DB, Transport, Auth, Access,
+                              CaCert, ClientCert, ClientKey, ServerName, Insecure, Proxy, Ssh, Date)
+         SELECT ?, Host, Sch, DBUser, NS, DB, Transport, Auth, Access,
+                CaCert, ClientCert, ClientKey, ServerName, Insecure, Proxy, Ssh, datetime('now', 'localtime')
+         FROM Profile WHERE Idx = ?",
+        [dst, src],
+    )?;
+    if let Some(vault) = db_profile_vault(src)? {
+        tx.execute(
+            "INSERT OR REPLACE INTO Vault (Idx, Nonce, Secret, Date)
+             SELECT ?, Nonce, Secret, datetime('now', 'localtime') FROM Vault WHERE Idx = ?",
+            [dst, &vault],
+        )?;
+        tx.execute("UPDATE Profile SET Vault = ? WHERE Idx = ?", [dst, dst])?;
+    }
+    tx.commit()?;
+
+    crate::utils::print_suc(&format!("{} cloned to {}.", src, dst));
+    Ok(())
+}
+
+/// Check a profile field value against its allowed choices, proxy or bastion syntax
+pub fn check_profile_value(field: &str, value: &str) -> Result<()> {
+    let allowed: &[&str] = match field {
+        "schema" => &["http", "https"],
+        "transport" => &["http", "ws"],
+        "auth" => &["root", "ns", "db", "record"],
+        "insecure" => &["on", "off"],
+        _ => &[],
+    };
+    if !allowed.is_empty() && !allowed.contains(&value) {
+        return Err(anyhow::anyhow!("Invalid value. Must be one of {}.", allowed.join(", ")));
+    }
+    match field {
+        "proxy" if value != "none" => crate::proxy::validate(value),
+        "ssh" if value != "none" => crate::tunnel::parse_bastion(value).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+/// Change a single field of a profile
+pub fn db_set_profile_field(name: &str, field: &str, value: &str) -> Result<()> {
+    if !db_valid_index(name)? {
+        return Err(anyhow::anyhow!("Profile {} does not exist.", name));
+    }
+
+    let Some((_, column)) = PROFILE_FIELDS.iter().find(|(f, _)| *f == field) else {
+        let fields: Vec<&str> = PROFILE_FIELDS.iter().map(|(f, _)| *f).collect();
+        return Err(anyhow::anyhow!("Invalid field. Must be one of {}.", fields.join(", ")));
+    };
+    check_profile_value(field, value)?;
+    let value = match (field, value) {
+        ("insecure", "on") => "1",
+        ("insecure", _) => "0",
+        // Paths, names and routes are cleared with none
+        ("ca_cert" | "client_cert" | "client_key" | "server_name" | "proxy" | "ssh", "none") => "",
+        _ => value,
+    };
+
+    let conn = get_conn()?;
+    conn.execute(&format!("UPDATE Profile SET {} = ? WHERE Idx = ?", column), [value, name])?;
+    crate::utils::print_suc(&format!("{}.{} <- {}", name, field, crate::dsn::redact(value)));
+    Ok(())
+}
+
+/// Vault entry referenced by a profile, if it stores a password
+pub fn db_profile_vault(idx: &str) -> Result<Option<String>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Vault FROM Profile WHERE Idx = ? AND Vault != ''")?;
+
+    let mut rows = stmt.query([idx])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// Point a profile at a vault entry
+pub fn db_set_profile_vault(idx: &str, vault: &str) -> Result<()> {
+    let conn = get_conn()?;
+    conn.execute("UPDATE Profile SET Vault = ? WHERE Idx = ?", [vault, idx])?;
+    Ok(())
+}
+
+/// Salt, nonce and passphrase verifier of the vault, if initialized
+pub fn db_vault_key() -> Result<Option<VaultKey>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Salt, Nonce, Verifier FROM VaultKey WHERE kid = 1")?;
+
+    let mut rows = stmt.query([])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(VaultKey {
+            salt: row.get(0)?,
+            nonce: row.get(1)?,
+            verifier: row.get(2)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+/// Nonce and ciphertext of a vault entry
+pub fn db_vault_entry(idx: &str) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Nonce, Secret FROM Vault WHERE Idx = ?")?;
+
+    let mut rows = stmt.query([idx])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?))),
+        None => Ok(None),
+    }
+}
+
+/// All encrypted vault entries
+pub fn db_vault_entries() -> Result<Vec<VaultEntry>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Idx, Nonce, Secret FROM Vault")?;
+    let entries = stmt
+        .query_map([], |row| {
+            Ok(VaultEntry {
+                idx: row.get(0)?,
+                nonce: row.get(1)?,
+                secret: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+/// Insert or replace an encrypted vault entry
+pub fn db_put_vault_entry(idx: &str, nonce: &[u8], secret: &[u8]) -> Result<()> {
+    let conn = get_conn()?;
+    conn.execute(
+        "INSERT INTO Vault (Idx, Nonce, Secret, Date) VALUES (?1, ?2, ?3, datetime('now', 'localtime'))
+         ON CONFLICT(Idx) DO UPDATE SET Nonce = ?2, Secret = ?3, Date = datetime('now', 'localtime')",
+        rusqlite::params![idx, nonce, secret],
+    )?;
+    Ok(())
+}
+
+/// Replace the vault key and re-encrypted entries in one transaction
+pub fn db_replace_vault(key: &VaultKey, entries: &[VaultEntry]) -> Result<()> {
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT OR REPLACE INTO VaultKey (kid, Salt, Nonce, Verifier) VALUES (1, ?, ?, ?)",
+        rusqlite::params![key.salt, key.nonce, key.verifier],
+    )?;
+    for entry in entries {
+        tx.execute(
+            "UPDATE Vault SET Nonce = ?, Secret = ? WHERE Idx = ?",
+            rusqlite::params![entry.nonce, entry.secret, entry.idx],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
 /// Check if query index exists
 pub fn db_valid_query_index(idx: &str) -> Result<bool> {
     let conn = get_conn()?;
@@ -218,9 +725,42 @@ pub fn db_valid_query_index(idx: &str) -> Result<bool> {
     Ok(exists)
 }
 
-/// Save current query
+/// Columns read into a `SavedQuery`
+const QUERY_COLUMNS: &str =
+    "qid, Idx, Query, Params, Description, Tags, Created, Updated, LastRun, Runs";
+
+/// Fields of a saved query that `.query set` can change
+pub const QUERY_FIELDS: &[&str] = &["description", "tags"];
+
+fn query_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SavedQuery> {
+    Ok(SavedQuery {
+        qid: row.get(0)?,
+        idx: row.get(1)?,
+        query: row.get(2)?,
+        params: row.get(3)?,
+        description: row.get(4)?,
+        tags: row.get(5)?,
+        created: row.get(6)?,
+        updated: row.get(7)?,
+        last_run: row.get(8)?,
+        runs: row.get(9)?,
+    })
+}
+
+/// Trim, drop empty and repeated tags and join them with commas
+pub fn normalize_tags(text: &str) -> String {
+    let mut tags: Vec<&str> = Vec::new();
+    for tag in text.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag);
+        }
+    }
+    tags.join(",")
+}
+
+/// Save current query with its parameter declarations, description and tags
 impl SurrDB {
-    pub fn db_save_query(&self, name: &str) -> Result<()> {
+    pub fn db_save_query(&self, name: &str, params: &str, description: &str, tags: &str) -> Result<()> {
         if self.query.is_empty() {
             crate::utils::print_err("No query to save.");
             return Ok(());
@@ -233,45 +773,225 @@ impl SurrDB {
 
         let conn = get_conn()?;
         conn.execute(
-            "INSERT INTO SQuery (Idx, Query) VALUES (?, ?)",
-            [name, &self.query],
+            "INSERT INTO SQuery (Idx, Query, Params, Description, Tags, Created, Updated)
+             VALUES (?, ?, ?, ?, ?, datetime('now', 'localtime'), datetime('now', 'localtime'))",
+            [name, &self.query, params, description, &normalize_tags(tags)],
         )?;
 
         crate::utils::print_suc("Query saved.");
         Ok(())
     }
-}
 
-/// Show all saved queries
-pub fn db_show_queries() -> Result<()> {
-    let conn = get_conn()?;
-    let mut stmt = conn.prepare("SELECT qid, Idx, Query FROM SQuery")?;
+    /// Show one saved query with its metadata and the full, formatted body
+    pub fn db_show_query(&self, name: &str) -> Result<()> {
+        let saved = db_get_query_by_idx(name)?;
 
-    let mut rows = stmt.query([])?;
+        let mut table = comfy_table::Table::new();
+        table.load_preset(comfy_table::presets::UTF8_FULL);
+        table.add_row(vec!["Name", &saved.idx]);
+        table.add_row(vec!["Description", &saved.description]);
+        table.add_row(vec!["Tags", &saved.tags.replace(',', ", ")]);
+        table.add_row(vec!["Params", &saved.params]);
+        table.add_row(vec!["Created", &saved.created]);
+        table.add_row(vec!["Updated", &saved.updated]);
+        table.add_row(vec!["Last run", &saved.last_run]);
+        table.add_row(vec!["Runs", &saved.runs.to_string()]);
+
+        println!();
+        println!("{}", table);
+        println!();
+        // One statement per line, highlighted like the prompt
+        for statement in crate::surql::split_statements(&saved.query) {
+            let statement = statement.trim();
+            if self.color {
+                println!("{}", crate::highlight::highlight_surql(statement, usize::MAX));
+            } else {
+                println!("{}", statement);
+            }
+        }
+        println!();
 
+        Ok(())
+    }
+}
+
+/// Compact listing of saved queries, long text cut to one short line
+fn print_queries(queries: &[SavedQuery]) {
     let mut table = comfy_table::Table::new();
     table.load_preset(comfy_table::presets::UTF8_FULL);
-    table.set_header(vec!["ID", "NAME", "QUERY"]);
-
-    while let Some(row) = rows.next()? {
-        let qid: i32 = row.get(0)?;
-        let idx: String = row.get(1)?;
-        let query: String = row.get(2)?;
+    table.set_header(vec!["ID", "NAME", "DESCRIPTION", "TAGS", "QUERY", "PARAMS", "RUNS", "LAST RUN"]);
 
+    for saved in queries {
+        let query = saved.query.split_whitespace().collect::<Vec<_>>().join(" ");
         table.add_row(vec![
-            qid.to_string(),
-            idx,
-            query,
+            saved.qid.to_string(),
+            saved.idx.clone(),
+            crate::render::truncate(&saved.description),
+            saved.tags.replace(',', ", "),
+            crate::render::truncate(&query),
+            crate::render::truncate(&saved.params),
+            saved.runs.to_string(),
+            saved.last_run.clone(),
         ]);
     }
 
     println!();
     println!("{}", table);
     println!();
+}
 
+/// Show all saved queries, or only those with a tag
+pub fn db_show_queries(tag: Option<&str>) -> Result<()> {
+    let queries: Vec<SavedQuery> = db_queries()?
+        .into_iter()
+        .filter(|saved| tag.is_none_or(|tag| saved.tags.split(',').any(|t| t.eq_ignore_ascii_case(tag))))
+        .collect();
+
+    if queries.is_empty() {
+        match tag {
+            Some(tag) => crate::utils::print_err(&format!("No saved query tagged {}.", tag)),
+            None => crate::utils::print_err("No saved queries."),
+        }
+        return Ok(());
+    }
+
+    print_queries(&queries);
     Ok(())
 }
 
+/// Fuzzy search saved queries by name, description, tags and text
+pub fn db_find_queries(text: &str) -> Result<()> {
+    let mut found: Vec<(i64, SavedQuery)> = db_queries()?
+        .into_iter()
+        .filter_map(|saved| {
+            // Name matches rank above matches in the other fields
+            let score = [
+                crate::utils::fuzzy_score(text, &saved.idx).map(|s| s * 2),
+                crate::utils::fuzzy_score(text, &saved.description),
+                crate::utils::fuzzy_score(text, &saved.tags),
+                crate::utils::fuzzy_score(text, &saved.query),
+            ]
+            .into_iter()
+            .flatten()
+            .max()?;
+            Some((score, saved))
+        })
+        .collect();
+
+    if found.is_empty() {
+        crate::utils::print_err(&format!("No saved query matches {}.", text));
+        return Ok(());
+    }
+
+    found.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.idx.cmp(&b.1.idx)));
+    let queries: Vec<SavedQuery> = found.into_iter().map(|(_, saved)| saved).collect();
+    print_queries(&queries);
+    Ok(())
+}
+
+/// All saved queries
+pub fn db_queries() -> Result<Vec<SavedQuery>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM SQuery ORDER BY qid", QUERY_COLUMNS))?;
+    let queries = stmt
+        .query_map([], query_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(queries)
+}
+
+/// Tags used by any saved query
+pub fn db_query_tags() -> Result<Vec<String>> {
+    let mut tags: Vec<String> = db_queries()?
+        .iter()
+        .flat_map(|saved| saved.tags.split(',').map(str::to_string).collect::<Vec<_>>())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    Ok(tags)
+}
+
+/// Change the description or tags of a saved query
+pub fn db_set_query_field(idx: &str, field: &str, value: &str) -> Result<()> {
+    if !db_valid_query_index(idx)? {
+        return Err(anyhow::anyhow!("Query {} does not exist.", idx));
+    }
+
+    let (column, value) = match field {
+        "description" => ("Description", value.to_string()),
+        "tags" => ("Tags", normalize_tags(value)),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unknown field {}. Use one of: {}",
+                field,
+                QUERY_FIELDS.join(", ")
+            ))
+        }
+    };
+
+    let conn = get_conn()?;
+    conn.execute(
+        &format!(
+            "UPDATE SQuery SET {} = ?, Updated = datetime('now', 'localtime') WHERE Idx = ?",
+            column
+        ),
+        [value.as_str(), idx],
+    )?;
+    crate::utils::print_suc(&format!("{} {} <- {}", idx, field, value));
+    Ok(())
+}
+
+/// Replace the text and parameter declarations of a saved query
+pub fn db_set_query_text(idx: &str, query: &str, params: &str) -> Result<()> {
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE SQuery SET Query = ?, Params = ?, Updated = datetime('now', 'localtime') WHERE Idx = ?",
+        [query, params, idx],
+    )?;
+    Ok(())
+}
+
+/// Count a run of a saved query
+pub fn db_record_query_run(idx: &str) -> Result<()> {
+    let conn = get_conn()?;
+    conn.execute(
+        "UPDATE SQuery SET Runs = Runs + 1, LastRun = datetime('now', 'localtime') WHERE Idx = ?",
+        [idx],
+    )?;
+    Ok(())
+}
+
+/// Insert a query, or replace the text and metadata of an existing one with the same name
+pub fn db_put_query(saved: &SavedQuery) -> Result<()> {
+    let conn = get_conn()?;
+    let tags = normalize_tags(&saved.tags);
+    let values = [&saved.query, &saved.params, &saved.description, &tags, &saved.idx];
+    if db_valid_query_index(&saved.idx)? {
+        conn.execute(
+            "UPDATE SQuery SET Query = ?, Params = ?, Description = ?, Tags = ?,
+             Updated = datetime('now', 'localtime') WHERE Idx = ?",
+            values,
+        )?;
+    } else {
+        conn.execute(
+            "INSERT INTO SQuery (Query, Params, Description, Tags, Idx, Created, Updated)
+             VALUES (?, ?, ?, ?, ?, datetime('now', 'localtime'), datetime('now', 'localtime'))",
+            values,
+        )?;
+    }
+    Ok(())
+}
+
+/// Names of all saved queries
+pub fn db_query_names() -> Result<Vec<String>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Idx FROM SQuery ORDER BY Idx")?;
+    let names = stmt
+        .query_map([], |row| row.get::<usize, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(names)
+}
+
 /// Delete a query by index
 pub fn db_drop_query_idx(idx: &str) -> Result<()> {
     if !db_valid_query_index(idx)? {
@@ -285,21 +1005,102 @@ pub fn db_drop_query_idx(idx: &str) -> Result<()> {
     Ok(())
 }
 
-/// Get query by index
-pub fn db_get_query_by_idx(idx: &str) -> Result<String> {
+/// Get query with its parameter declarations and metadata by index
+pub fn db_get_query_by_idx(idx: &str) -> Result<SavedQuery> {
     if !db_valid_query_index(idx)? {
-        crate::utils::print_err("Query does not exist.");
-        return Err(anyhow::anyhow!("Query not found"));
+        return Err(anyhow::anyhow!("Query {} does not exist.", idx));
     }
 
     let conn = get_conn()?;
-    let mut stmt = conn.prepare("SELECT Query FROM SQuery WHERE Idx = ?")?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM SQuery WHERE Idx = ?", QUERY_COLUMNS))?;
 
     let mut rows = stmt.query([idx])?;
-    if let Some(row) = rows.next()? {
-        let query: String = row.get(0)?;
-        Ok(query)
-    } else {
-        Err(anyhow::anyhow!("Query not found"))
+    match rows.next()? {
+        Some(row) => Ok(query_from_row(row)?),
+        None => Err(anyhow::anyhow!("Query not found")),
+    }
+}
+
+/// Record an executed statement and, when given, its output
+impl SurrDB {
+    pub fn db_log_statement(&self, query: &str, duration: u128, status: &str, output: Option<&str>, size: usize) -> Result<i64> {
+        let conn = get_conn()?;
+        conn.execute(
+            "INSERT INTO Sess (Profile, Host, NS, DB, Query, Date, Duration, Status) VALUES (?, ?, ?, ?, ?, datetime('now', 'localtime'), ?, ?)",
+            rusqlite::params![self.profile, self.host, self.namespace, self.database, query, duration as i64, status],
+        )?;
+        let sid = conn.last_insert_rowid();
+
+        conn.execute(
+            "INSERT INTO SOut (Sid, Output, Size) VALUES (?, ?, ?)",
+            rusqlite::params![sid, output, size as i64],
+        )?;
+        Ok(sid)
+    }
+}
+
+/// Logged statements, newest last, optionally filtered by text
+pub fn db_log_entries(limit: usize, search: Option<&str>) -> Result<Vec<LogEntry>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT sid, Profile, Host, NS, DB, Query, Date, Duration, Status FROM Sess
+         WHERE Query != '' AND (?1 IS NULL OR instr(lower(Query), lower(?1)) > 0)
+         ORDER BY sid DESC LIMIT ?2",
+    )?;
+    let mut entries = stmt
+        .query_map(rusqlite::params![search, limit as i64], |row| {
+            Ok(LogEntry {
+                sid: row.get(0)?,
+                profile: row.get(1)?,
+                host: row.get(2)?,
+                ns: row.get(3)?,
+                db: row.get(4)?,
+                query: row.get(5)?,
+                date: row.get(6)?,
+                duration: row.get(7)?,
+                status: row.get(8)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    entries.reverse();
+    Ok(entries)
+}
+
+/// Stored output of a logged statement with its size.
+/// The output is `None` when it was over the size cap or not kept.
+pub fn db_log_output(sid: i64) -> Result<Option<(Option<String>, i64)>> {
+    let conn = get_conn()?;
+    let mut stmt = conn.prepare("SELECT Output, Size FROM SOut WHERE Sid = ?")?;
+
+    let mut rows = stmt.query([sid])?;
+    match rows.next()? {
+        Some(row) => Ok(Some((row.get(0)?, row.get(1)?))),
+        None => Ok(None),
+    }
+}
+
+/// Id of the most recently logged statement
+pub fn db_last_log_id() -> Result<Option<i64>> {
+    let conn = get_conn()?;
+    let sid = conn.query_row("SELECT max(sid) FROM Sess WHERE Query != ''", [], |row| row.get(0))?;
+    Ok(sid)
+}
+
+/// Drop log entries beyond the newest `keep` or older than `days`
+pub fn db_prune_log(keep: usize, days: u64) -> Result<usize> {
+    let conn = get_conn()?;
+    let tx = conn.unchecked_transaction()?;
+    let mut removed = tx.execute(
+        "DELETE FROM Sess WHERE sid NOT IN (SELECT sid FROM Sess ORDER BY sid DESC LIMIT ?)",
+        [keep as i64],
+    )?;
+    if days > 0 {
+        removed += tx.execute(
+            "DELETE FROM Sess WHERE Date != '' AND Date < datetime('now', 'localtime', ?)",
+            [format!("-{} days", days)],
+        )?;
     }
+    tx.execute("DELETE FROM SOut WHERE Sid != 0 AND Sid NOT IN (SELECT sid FROM Sess)", [])?;
+    tx.commit()?;
+    Ok(removed)
 }
@@ -0,0 +1,149 @@
+use crate::models::SurrDB;
+use crate::params::{format_specs, parse_specs, query_params};
+use crate::utils::{print_err, print_suc};
+use anyhow::Result;
+use rustyline::{Cmd, ConditionalEventHandler, Event, EventContext, Movement, RepeatCount};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Editor used when neither $VISUAL nor $EDITOR is set
+#[cfg(windows)]
+const FALLBACK_EDITOR: &str = "notepad";
+#[cfg(not(windows))]
+const FALLBACK_EDITOR: &str = "vi";
+
+/// Editor command from $VISUAL or $EDITOR, split into program and arguments
+fn editor_command() -> Vec<String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| FALLBACK_EDITOR.to_string());
+    editor.split_whitespace().map(str::to_string).collect()
+}
+
+/// Temp files created by this process so far
+static TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Create a new temp file readable only by the user. `create_new` keeps another
+/// user from planting the file or a symlink first; a taken name moves on to the next.
+fn create_temp() -> Result<(PathBuf, File)> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.subsec_nanos());
+    for _ in 0..8 {
+        let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let name = format!("surrcli-{}-{:08x}-{}.surql", std::process::id(), nanos, count);
+        let path = std::env::temp_dir().join(name);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(anyhow::anyhow!("Cannot create {}: {}", path.display(), e)),
+        }
+    }
+    Err(anyhow::anyhow!("Cannot create a temp file in {}", std::env::temp_dir().display()))
+}
+
+/// Open text in the editor and return it after saving, or None when the file
+/// was left unsaved
+pub fn edit_text(initial: &str) -> Result<Option<String>> {
+    let (path, mut file) = create_temp()?;
+    let mut content = initial.trim_end().to_string();
+    content.push('\n');
+    let written = file.write_all(content.as_bytes());
+    drop(file);
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&path);
+        return Err(e.into());
+    }
+    let before = std::fs::metadata(&path)?.modified()?;
+
+    let command = editor_command();
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .arg(&path)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Cannot start editor {}: {}", command[0], e));
+
+    let result = status.and_then(|status| {
+        if !status.success() {
+            return Err(anyhow::anyhow!("Editor {} exited with {}", command[0], status));
+        }
+        if std::fs::metadata(&path)?.modified()? == before {
+            return Ok(None);
+        }
+        Ok(Some(std::fs::read_to_string(&path)?.trim_end().to_string()))
+    });
+
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+/// Ctrl-X Ctrl-E: hand the current buffer to the editor and take the result back
+pub struct EditBuffer;
+
+impl ConditionalEventHandler for EditBuffer {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        match edit_text(&crate::cli::strip_continuation(ctx.line())) {
+            Ok(Some(text)) => Some(Cmd::Replace(
+                Movement::WholeBuffer,
+                Some(crate::cli::pad_continuation(&text)),
+            )),
+            Ok(None) => Some(Cmd::Repaint),
+            Err(e) => {
+                print_err(&e.to_string());
+                Some(Cmd::Repaint)
+            }
+        }
+    }
+}
+
+impl SurrDB {
+    /// `.edit`: open the last query in the editor and run it once saved
+    pub fn edit_last(&mut self) -> Result<()> {
+        let Some(text) = edit_text(&self.query)? else {
+            print_err("Edit cancelled.");
+            return Ok(());
+        };
+        if crate::surql::only_comments(&text) {
+            print_err("Nothing to run.");
+            return Ok(());
+        }
+
+        println!("{}", text);
+        self.execute(&text)
+    }
+
+    /// `.edit query <name>`: edit a saved query in place
+    pub fn edit_saved_query(&self, name: &str) -> Result<()> {
+        let saved = crate::db::db_get_query_by_idx(name)?;
+        let Some(text) = edit_text(&saved.query)? else {
+            print_err("Edit cancelled.");
+            return Ok(());
+        };
+        if crate::surql::only_comments(&text) {
+            return Err(anyhow::anyhow!("A saved query cannot be empty."));
+        }
+
+        // Declarations of parameters the new text no longer reads are dropped
+        let used = query_params(&text);
+        let declared: Vec<_> = parse_specs(&saved.params)?
+            .into_iter()
+            .filter(|spec| used.contains(&spec.name))
+            .collect();
+        crate::db::db_set_query_text(name, &text, &format_specs(&declared))?;
+
+        print_suc(&format!("Query {} updated.", name));
+        Ok(())
+    }
+}
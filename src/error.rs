@@ -0,0 +1,49 @@
+/// Failures reported to the user, each mapped to its own process exit code
+#[derive(Debug, thiserror::Error)]
+pub enum SurrError {
+    #[error("Cannot connect to SurrealDB: {0}")]
+    Connection(String),
+    #[error("Authentication failed (HTTP {status}): {details}")]
+    Auth { status: u16, details: String },
+    #[error("Query rejected (HTTP {status}): {details}")]
+    Rejected { status: u16, details: String },
+    #[error("{0} statement(s) failed")]
+    Statement(usize),
+    #[error("Server error (HTTP {status}): {details}")]
+    Server { status: u16, details: String },
+}
+
+impl SurrError {
+    /// Exit code for scripts: 2 connection, 3 auth, 4 statement, 5 server
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SurrError::Connection(_) => 2,
+            SurrError::Auth { .. } => 3,
+            SurrError::Rejected { .. } | SurrError::Statement(_) => 4,
+            SurrError::Server { .. } => 5,
+        }
+    }
+
+    /// Classify a non-200 HTTP response
+    pub fn from_status(status: u16, body: &str) -> Self {
+        let details = error_details(body);
+        match status {
+            401 | 403 => SurrError::Auth { status, details },
+            400..=499 => SurrError::Rejected { status, details },
+            _ => SurrError::Server { status, details },
+        }
+    }
+}
+
+/// Extract the human readable message from a SurrealDB error body
+pub fn error_details(body: &str) -> String {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return body.trim().to_string();
+    };
+
+    ["information", "details", "description"]
+        .iter()
+        .find_map(|key| value[key].as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| body.trim().to_string())
+}
@@ -0,0 +1,133 @@
+use crate::surql::{is_keyword, tokenize, Token, TokenKind};
+use colored::Colorize;
+
+/// Check whether the terminal can show colors (NO_COLOR, TERM=dumb)
+pub fn color_supported() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        && std::env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+/// Color a line of SurrealQL and mark the bracket pair around the cursor
+pub fn highlight_surql(line: &str, pos: usize) -> String {
+    let tokens = tokenize(line);
+    let pair = matching_bracket(&tokens, pos);
+    let mut out = String::with_capacity(line.len() * 2);
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+
+        if token.kind == TokenKind::Ident {
+            let path_end = path_end(&tokens, i);
+            if path_end > i {
+                // fn::custom or builtin string::len calls
+                let end = tokens[path_end].end();
+                out.push_str(&line[token.start..end].yellow().to_string());
+                i = path_end + 1;
+                continue;
+            }
+            if let Some(id_end) = record_id_end(&tokens, i) {
+                let end = tokens[id_end].end();
+                out.push_str(&line[token.start..end].cyan().to_string());
+                i = id_end + 1;
+                continue;
+            }
+        }
+
+        let styled = match token.kind {
+            TokenKind::Whitespace => token.text.normal(),
+            TokenKind::Comment => token.text.bright_black().italic(),
+            TokenKind::String => token.text.green(),
+            TokenKind::Number => token.text.bright_magenta(),
+            TokenKind::Param => token.text.bright_cyan(),
+            TokenKind::Ident if is_keyword(token.text) => token.text.bright_blue().bold(),
+            TokenKind::Ident => token.text.normal(),
+            TokenKind::Punct if pair.is_some_and(|(a, b)| token.start == a || token.start == b) => {
+                token.text.bold().reversed()
+            }
+            TokenKind::Punct if is_operator(token.text) => token.text.red(),
+            TokenKind::Punct => token.text.normal(),
+        };
+        out.push_str(&styled.to_string());
+        i += 1;
+    }
+
+    out
+}
+
+/// Characters drawn as operators
+fn is_operator(text: &str) -> bool {
+    matches!(
+        text,
+        "=" | "!" | "<" | ">" | "+" | "-" | "*" | "/" | "?" | "|" | "&" | "~" | "@" | "∋" | "∈"
+    )
+}
+
+fn is_punct(tokens: &[Token<'_>], i: usize, text: &str) -> bool {
+    tokens
+        .get(i)
+        .is_some_and(|t| t.kind == TokenKind::Punct && t.text == text)
+}
+
+/// Index of the last identifier in `a::b::c`, or `i` when not a path
+fn path_end(tokens: &[Token<'_>], i: usize) -> usize {
+    let mut end = i;
+    while is_punct(tokens, end + 1, ":")
+        && is_punct(tokens, end + 2, ":")
+        && tokens
+            .get(end + 3)
+            .is_some_and(|t| t.kind == TokenKind::Ident)
+    {
+        end += 3;
+    }
+    end
+}
+
+/// Index of the id part of a `table:id` record id
+fn record_id_end(tokens: &[Token<'_>], i: usize) -> Option<usize> {
+    if !is_punct(tokens, i + 1, ":") || is_punct(tokens, i + 2, ":") {
+        return None;
+    }
+    tokens
+        .get(i + 2)
+        .filter(|t| matches!(t.kind, TokenKind::Ident | TokenKind::Number))
+        .map(|_| i + 2)
+}
+
+/// Byte offsets of the bracket at or before the cursor and its partner
+fn matching_bracket(tokens: &[Token<'_>], pos: usize) -> Option<(usize, usize)> {
+    let brackets: Vec<&Token<'_>> = tokens
+        .iter()
+        .filter(|t| t.kind == TokenKind::Punct && "()[]{}".contains(t.text))
+        .collect();
+
+    let at = brackets
+        .iter()
+        .position(|t| t.start == pos)
+        .or_else(|| brackets.iter().position(|t| t.end() == pos))?;
+
+    let (open, close) = match brackets[at].text {
+        "(" | ")" => ("(", ")"),
+        "[" | "]" => ("[", "]"),
+        _ => ("{", "}"),
+    };
+
+    let mut depth = 0;
+    if brackets[at].text == open {
+        for t in &brackets[at..] {
+            depth += if t.text == open { 1 } else if t.text == close { -1 } else { 0 };
+            if depth == 0 {
+                return Some((brackets[at].start, t.start));
+            }
+        }
+    } else {
+        for t in brackets[..=at].iter().rev() {
+            depth += if t.text == close { 1 } else if t.text == open { -1 } else { 0 };
+            if depth == 0 {
+                return Some((t.start, brackets[at].start));
+            }
+        }
+    }
+
+    None
+}
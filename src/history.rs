@@ -0,0 +1,208 @@
+use crate::db::{db_last_log_id, db_log_entries, db_log_output, db_prune_log};
+use crate::models::SurrDB;
+use crate::utils::{print_err, print_suc};
+use anyhow::Result;
+use colored::Colorize;
+use rustyline::history::{DefaultHistory, History};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Number of entries shown by `.history` and `.log` without an argument
+const HISTORY_SHOWN: usize = 20;
+
+/// Most entries shown for a `.log search`
+const SEARCH_LIMIT: usize = 1000;
+
+const HISTORY_USAGE: &str = "Usage: .history [n|search <text>]";
+
+const LOG_USAGE: &str = "Usage: .log [n|search <text>|prune]";
+
+/// Words that mark a statement as carrying credentials
+const SECRET_WORDS: &[&str] = &["PASSWORD", "PASSHASH"];
+
+/// Check whether a line contains credentials that should not be persisted
+pub fn contains_secret(line: &str) -> bool {
+    crate::surql::tokenize(line)
+        .iter()
+        .any(|t| SECRET_WORDS.iter().any(|w| t.is_word(w)))
+}
+
+/// Replace characters that are not safe in file names
+fn file_key(key: &str) -> String {
+    key.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Drop older duplicates, keeping the most recent occurrence of each entry
+pub fn dedup_history(history: &mut DefaultHistory) -> Result<()> {
+    let entries: Vec<String> = history.iter().cloned().collect();
+    let mut seen = HashSet::new();
+    let mut kept: Vec<&String> = entries.iter().rev().filter(|e| seen.insert(*e)).collect();
+    kept.reverse();
+
+    if kept.len() != entries.len() {
+        history.clear()?;
+        for entry in kept {
+            history.add(entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl SurrDB {
+    /// History file for the configured scope (global, profile or host)
+    pub fn history_path(&self) -> Result<PathBuf> {
+        let data = dirs::data_dir().ok_or_else(|| anyhow::anyhow!("Cannot find data directory"))?;
+        let dir = data.join("surrcli");
+        std::fs::create_dir_all(&dir)?;
+
+        let name = match self.history_scope.as_str() {
+            "profile" if !self.profile.is_empty() => format!("history-{}", file_key(&self.profile)),
+            "profile" | "host" => format!("history-{}", file_key(&self.host)),
+            _ => "history".to_string(),
+        };
+
+        Ok(dir.join(name))
+    }
+
+    /// Record an executed statement in the local log, keeping outputs under the size cap
+    pub fn log_execution(&self, query: &str, started: Instant, status: &str, output: Option<&str>) {
+        if !self.log || (!self.history_secrets && contains_secret(query)) {
+            return;
+        }
+
+        let size = output.map_or(0, str::len);
+        let kept = output.filter(|o| o.len() <= self.log_output);
+        let elapsed = started.elapsed().as_millis();
+        if let Err(e) = self.db_log_statement(&crate::dsn::redact(query), elapsed, status, kept, size) {
+            print_err(&format!("Cannot log statement: {}", e));
+        }
+    }
+
+    /// Print the last entries of the history or those matching a search
+    pub fn show_history(&self, history: &DefaultHistory, args: &[&str]) {
+        let entries: Vec<(usize, &String)> = history.iter().enumerate().collect();
+
+        let shown: Vec<(usize, &String)> = match args {
+            [] => tail(&entries, HISTORY_SHOWN),
+            ["search", words @ ..] if !words.is_empty() => {
+                let needle = words.join(" ").to_lowercase();
+                entries
+                    .into_iter()
+                    .filter(|(_, e)| e.to_lowercase().contains(&needle))
+                    .collect()
+            }
+            [n] => match n.parse::<usize>() {
+                Ok(n) => tail(&entries, n),
+                Err(_) => {
+                    print_err(HISTORY_USAGE);
+                    return;
+                }
+            },
+            _ => {
+                print_err(HISTORY_USAGE);
+                return;
+            }
+        };
+
+        println!();
+        for (i, entry) in shown {
+            println!("{:>5}  {}", i + 1, entry.replace('\n', "\n       "));
+        }
+        println!();
+    }
+
+    /// Print the last logged statements or those matching a search
+    pub fn show_log(&self, args: &[&str]) {
+        let entries = match args {
+            [] => db_log_entries(HISTORY_SHOWN, None),
+            ["search", words @ ..] if !words.is_empty() => db_log_entries(SEARCH_LIMIT, Some(&words.join(" "))),
+            ["prune"] => {
+                match db_prune_log(self.log_keep, self.log_days) {
+                    Ok(n) => print_suc(&format!("{} log entries removed.", n)),
+                    Err(e) => print_err(&format!("Cannot prune log: {}", e)),
+                }
+                return;
+            }
+            [n] => match n.parse::<usize>() {
+                Ok(n) => db_log_entries(n, None),
+                Err(_) => {
+                    print_err(LOG_USAGE);
+                    return;
+                }
+            },
+            _ => {
+                print_err(LOG_USAGE);
+                return;
+            }
+        };
+        let entries = match entries {
+            Ok(entries) => entries,
+            Err(e) => {
+                print_err(&format!("Cannot read log: {}", e));
+                return;
+            }
+        };
+
+        println!();
+        for entry in entries {
+            let status = if entry.status == "OK" {
+                entry.status.green()
+            } else {
+                entry.status.red()
+            };
+            let target = if entry.profile.is_empty() {
+                format!("{} {}/{}", entry.host, entry.ns, entry.db)
+            } else {
+                format!("{} {}/{}", entry.profile, entry.ns, entry.db)
+            };
+            println!(
+                "{:>5}  {}  {}  {}ms  {}",
+                entry.sid,
+                entry.date.bright_black(),
+                target.bright_black(),
+                entry.duration,
+                status
+            );
+            println!("       {}", entry.query.trim().replace('\n', "\n       "));
+        }
+        println!();
+        if !self.log {
+            print_err("Logging is off, new statements are not recorded. Use .set log on.");
+        }
+    }
+
+    /// Show the stored output of a logged statement again
+    pub fn show_output(&self, sid: i64) {
+        match db_log_output(sid) {
+            Ok(Some((Some(output), _))) => {
+                if let Err(e) = self.print_response(&output) {
+                    print_err(&format!("Cannot show output: {}", e));
+                }
+            }
+            Ok(Some((None, 0))) => print_err(&format!("Statement {} has no stored output.", sid)),
+            Ok(Some((None, size))) => print_err(&format!(
+                "The output of statement {} was {} bytes, over the log_output limit, and was not kept.",
+                sid, size
+            )),
+            Ok(None) => print_err(&format!("No logged statement {}.", sid)),
+            Err(e) => print_err(&format!("Cannot read log: {}", e)),
+        }
+    }
+
+    /// Show the output of the most recent statement
+    pub fn show_last(&self) {
+        match db_last_log_id() {
+            Ok(Some(sid)) => self.show_output(sid),
+            Ok(None) => print_err("No statement has been logged yet."),
+            Err(e) => print_err(&format!("Cannot read log: {}", e)),
+        }
+    }
+}
+
+fn tail<'a>(entries: &[(usize, &'a String)], n: usize) -> Vec<(usize, &'a String)> {
+    entries[entries.len().saturating_sub(n)..].to_vec()
+}
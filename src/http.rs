@@ -0,0 +1,152 @@
+use crate::models::SurrDB;
+use anyhow::Result;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use std::time::Duration;
+
+/// Answers from a proxy or a restarting server that are worth another try
+const RETRY_STATUS: &[u16] = &[502, 503, 504];
+
+/// Check whether a request failed because nothing accepted the connection
+fn connection_refused(err: &reqwest::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+    while let Some(e) = source {
+        if let Some(io) = e.downcast_ref::<std::io::Error>()
+            && io.kind() == std::io::ErrorKind::ConnectionRefused
+        {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+/// What one attempt gave, as far as retrying is concerned
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Attempt {
+    Answered(u16),
+    Refused,
+    Failed,
+}
+
+fn attempt_of(result: &reqwest::Result<Response>) -> Attempt {
+    match result {
+        Ok(response) => Attempt::Answered(response.status().as_u16()),
+        Err(e) if connection_refused(e) => Attempt::Refused,
+        Err(_) => Attempt::Failed,
+    }
+}
+
+/// Check whether an attempt is worth another try
+fn should_retry(attempt: Attempt, retry_unavailable: bool) -> bool {
+    match attempt {
+        Attempt::Refused => true,
+        Attempt::Answered(status) => retry_unavailable && RETRY_STATUS.contains(&status),
+        Attempt::Failed => false,
+    }
+}
+
+/// Call `send` until it gives something not worth retrying or `retries` retries
+/// are spent, sleeping `delay` before the first retry and twice as long each time after
+fn with_retries<T>(
+    retries: u32,
+    delay: Duration,
+    retry_unavailable: bool,
+    mut send: impl FnMut() -> T,
+    attempt: impl Fn(&T) -> Attempt,
+    mut sleep: impl FnMut(Duration),
+) -> T {
+    let mut delay = delay;
+    for _ in 0..retries {
+        let result = send();
+        if !should_retry(attempt(&result), retry_unavailable) {
+            return result;
+        }
+        sleep(delay);
+        delay = delay.saturating_mul(2);
+    }
+    send()
+}
+
+impl SurrDB {
+    /// HTTP client of the session, built on first use so connections are kept alive
+    pub fn http_client(&self) -> Result<Client> {
+        if let Some(client) = self.http.borrow().as_ref() {
+            return Ok(client.clone());
+        }
+        let client = self.build_http_client()?;
+        *self.http.borrow_mut() = Some(client.clone());
+        Ok(client)
+    }
+
+    /// Drop the HTTP client so the next request connects with the current settings
+    pub fn reset_http(&self) {
+        self.http.borrow_mut().take();
+    }
+
+    /// Send a request, retrying refused connections with a delay that doubles
+    /// after each attempt. A 502/503/504 answer may come after the server ran
+    /// the statements, so it is only retried with `retry_unavailable`.
+    pub fn send(&self, request: impl Fn(&Client) -> RequestBuilder) -> Result<Response> {
+        let client = self.http_client()?;
+        let response = with_retries(
+            self.retries,
+            Duration::from_millis(self.retry_delay),
+            self.retry_unavailable,
+            || request(&client).send(),
+            attempt_of,
+            std::thread::sleep,
+        );
+        Ok(response?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Attempts and sleeps of a retry run over scripted outcomes
+    fn run(outcomes: &[Attempt], retries: u32, retry_unavailable: bool) -> (Attempt, usize, Vec<u64>) {
+        let mut next = outcomes.iter().copied();
+        let mut sent = 0;
+        let mut slept = Vec::new();
+        let last = with_retries(
+            retries,
+            Duration::from_millis(100),
+            retry_unavailable,
+            || {
+                sent += 1;
+                next.next().unwrap_or(Attempt::Answered(200))
+            },
+            |attempt| *attempt,
+            |delay| slept.push(delay.as_millis() as u64),
+        );
+        (last, sent, slept)
+    }
+
+    #[test]
+    fn refused_connections_back_off_up_to_the_retry_limit() {
+        let refused = [Attempt::Refused; 10];
+        assert_eq!(run(&refused, 3, false), (Attempt::Refused, 4, vec![100, 200, 400]));
+        assert_eq!(
+            run(&[Attempt::Refused, Attempt::Answered(200)], 3, false),
+            (Attempt::Answered(200), 2, vec![100])
+        );
+        assert_eq!(run(&refused, 0, false), (Attempt::Refused, 1, vec![]));
+    }
+
+    #[test]
+    fn unavailable_answers_are_retried_only_when_asked() {
+        for status in [502, 503, 504] {
+            let answers = [Attempt::Answered(status), Attempt::Answered(200)];
+            assert_eq!(run(&answers, 2, false), (Attempt::Answered(status), 1, vec![]));
+            assert_eq!(run(&answers, 2, true), (Attempt::Answered(200), 2, vec![100]));
+        }
+    }
+
+    #[test]
+    fn other_answers_and_errors_are_never_retried() {
+        for attempt in [Attempt::Answered(200), Attempt::Answered(400), Attempt::Answered(500), Attempt::Failed] {
+            assert_eq!(run(&[attempt], 3, true), (attempt, 1, vec![]));
+        }
+    }
+}
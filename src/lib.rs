@@ -1,6 +1,25 @@
+pub mod auth;
 pub mod cli;
+pub mod config;
 pub mod db;
+pub mod dsn;
+pub mod editor;
+pub mod error;
+pub mod highlight;
+pub mod history;
+pub mod http;
 pub mod meta;
 pub mod models;
+pub mod params;
+pub mod proxy;
+pub mod render;
 pub mod requester;
+pub mod rpc;
+pub mod schema;
+pub mod script;
+pub mod surql;
+pub mod tls;
+pub mod transfer;
+pub mod tunnel;
 pub mod utils;
+pub mod vault;
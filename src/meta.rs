@@ -20,12 +20,29 @@ pub fn help() {
         .set_header(vec!["COMMAND", "DESCRIPTION"]);
 
     table.add_row(vec![".help", "Show help menu"]);
-    table.add_row(vec![".options", "Env variables"]);
+    table.add_row(vec![".options", "Env variables [--sources]"]);
     table.add_row(vec![".set", "Set variable"]);
-    table.add_row(vec![".save", "Save profile|query"]);
-    table.add_row(vec![".show", "Show profiles|queries"]);
+    table.add_row(vec![".use", "Switch namespace [database]"]);
+    table.add_row(vec![".connect", "Connect with a string: wss://user@host:8000/ns/db?timeout=10"]);
+    table.add_row(vec![".save", "Save profile [--with-password]|query [param[:type][=default]] [--tags a,b] [--desc text]"]);
+    table.add_row(vec![".show", "Show profiles|queries [--tag <tag>]|query <name>"]);
+    table.add_row(vec![".edit", "Edit the last query in $EDITOR and run it, or edit query <name>"]);
+    table.add_row(vec![".find", "Fuzzy search saved queries: query <text>"]);
     table.add_row(vec![".delete", "Delete profile|query"]);
-    table.add_row(vec![".run", "Run profile|query"]);
+    table.add_row(vec![".run", "Run profile|query [name=value]"]);
+    table.add_row(vec![".history", "Show the input history [n|search <text>]"]);
+    table.add_row(vec![".log", "Show logged statements [n|search <text>|prune]"]);
+    table.add_row(vec![".live", "Start a live query [table] and watch notifications (ws)"]);
+    table.add_row(vec![".kill", "Stop a live query <id>"]);
+    table.add_row(vec![".last", "Show the last result again"]);
+    table.add_row(vec![".output", "Show the result of a logged statement <id>"]);
+    table.add_row(vec![".source", "Run a .surql file [--continue-on-error]"]);
+    table.add_row(vec![".vault", "Password vault unlock|lock|rotate"]);
+    table.add_row(vec![".let", "Set session parameter <name> = <value>"]);
+    table.add_row(vec![".unset", "Remove session parameter"]);
+    table.add_row(vec![".params", "Show session parameters"]);
+    table.add_row(vec![".profile", "Profile update|rename|clone|set|default"]);
+    table.add_row(vec![".query", "Saved query set <name> description|tags <value>"]);
 
     println!();
     println!("{}", table);
@@ -40,16 +57,48 @@ pub fn help_cmd() {
         .set_content_arrangement(ContentArrangement::Dynamic)
         .set_header(vec!["COMMAND", "DESCRIPTION", "DEFAULT"]);
 
+    table.add_row(vec!["DSN|-url", "Connection string ws[s]|http[s]://user:pass@host/ns/db", "none"]);
     table.add_row(vec!["-u", "Username", "root"]);
     table.add_row(vec!["-p", "Password", "hide password"]);
     table.add_row(vec!["-host", "Database address \"IP:PORT\"", "0.0.0.0:80"]);
     table.add_row(vec!["-NS", "Namespace", "surr"]);
     table.add_row(vec!["-DB", "Database", "surr"]);
     table.add_row(vec!["-sc", "Schema", "http"]);
+    table.add_row(vec!["-transport", "Transport (http or ws)", "http"]);
+    table.add_row(vec!["-auth", "Signin level (root|ns|db|record)", "root"]);
+    table.add_row(vec!["-access", "Record access method", "none"]);
     table.add_row(vec!["-profile", "Connect to a profile", "none"]);
-    table.add_row(vec!["-t", "Connection timeout", "5"]);
+    table.add_row(vec!["-ca-cert", "Trusted CA bundle (PEM)", "none"]);
+    table.add_row(vec!["-client-cert", "Client certificate for mTLS (PEM)", "none"]);
+    table.add_row(vec!["-client-key", "Client key (PKCS#8 PEM)", "none"]);
+    table.add_row(vec!["-server-name", "TLS name instead of the host", "none"]);
+    table.add_row(vec!["-insecure", "Skip certificate checks (unsafe)", "false"]);
+    table.add_row(vec!["-proxy", "http|https|socks5(h)://proxy or direct", "env"]);
+    table.add_row(vec!["-ssh", "SSH tunnel through [user@]bastion[:port]", "none"]);
+    table.add_row(vec!["-settings", "Named settings from config.toml", "none"]);
+    table.add_row(vec!["-f", "Run a .surql script file", "none"]);
+    table.add_row(vec!["-saved", "Run a saved query", "none"]);
+    table.add_row(vec!["-param", "Saved query parameter name=value", "none"]);
+    table.add_row(vec!["-continue-on-error", "Keep running after a failed statement", "false"]);
+    table.add_row(vec!["-stdin-mode", "Send stdin whole or each statement", "whole"]);
+    table.add_row(vec!["-batch-size", "Statements per request for stdin", "0"]);
+    table.add_row(vec!["-t", "Request timeout", "5"]);
+    table.add_row(vec!["-connect-timeout", "Connection timeout", "5"]);
+    table.add_row(vec!["-retries", "Retries on refused connections", "2"]);
+    table.add_row(vec!["-retry-unavailable", "Also retry 502/503/504 (may repeat writes)", "false"]);
+    table.add_row(vec!["-retry-delay", "First retry delay (ms), doubled", "200"]);
     table.add_row(vec!["-pretty", "Pretty output", "true"]);
+    table.add_row(vec!["-format", "json|table|raw|csv|ndjson|yaml|markdown", "json"]);
+    table.add_row(vec!["-meta", "Include statement metadata", "false"]);
     table.add_row(vec!["-comp", "Number of suggestions", "5"]);
+    table.add_row(vec!["-history-size", "History entries kept on disk", "1000"]);
+    table.add_row(vec!["-history-scope", "History per global|profile|host", "global"]);
+    table.add_row(vec!["-history-secrets", "Keep statements with credentials", "false"]);
+    table.add_row(vec!["-no-log", "Do not log executed statements", "false"]);
+    table.add_row(vec!["-log-output", "Largest result kept in the log (bytes)", "65536"]);
+    table.add_row(vec!["-log-keep", "Logged statements kept", "5000"]);
+    table.add_row(vec!["-log-days", "Days logged statements are kept", "90"]);
+    table.add_row(vec!["-no-color", "Disable colors and highlighting", "false"]);
 
     println!();
     println!("{}", table);
@@ -58,21 +107,84 @@ pub fn help_cmd() {
 
 /// Print current options
 impl SurrDB {
-    pub fn show_options(&self) {
+    /// With `sources`, also show where each layered value came from
+    pub fn show_options(&self, sources: bool) {
+        let mut rows: Vec<(&str, String, Option<&str>)> = vec![
+            ("URL", self.dsn(), None),
+            ("Host", self.host.clone(), Some("host")),
+            ("User", self.user.clone(), Some("user")),
+            ("Namespace", self.namespace.clone(), Some("namespace")),
+            ("Database", self.database.clone(), Some("database")),
+            ("Schema", self.schema.clone(), Some("schema")),
+            ("Transport", self.transport.clone(), Some("transport")),
+            ("Auth", self.auth.clone(), Some("auth")),
+        ];
+        if self.auth == "record" {
+            rows.push(("Access", self.access.clone(), Some("access")));
+        }
+        let tls = &self.tls;
+        if self.schema == "https" || *tls != crate::models::TlsOptions::default() {
+            rows.push(("CA cert", tls.ca_cert.clone(), Some("ca_cert")));
+            rows.push(("Client cert", tls.client_cert.clone(), Some("client_cert")));
+            rows.push(("Client key", tls.client_key.clone(), Some("client_key")));
+            rows.push(("Server name", tls.server_name.clone(), Some("server_name")));
+            rows.push(("Insecure", if tls.insecure { "ON" } else { "off" }.to_string(), Some("insecure")));
+        }
+        if !self.proxy.is_empty() {
+            rows.push(("Proxy", crate::dsn::redact(&self.proxy), Some("proxy")));
+        }
+        if !self.ssh.is_empty() {
+            let ssh = match self.tunnel.borrow().as_ref() {
+                Some(tunnel) => format!("{} (127.0.0.1:{})", self.ssh, tunnel.port),
+                None => self.ssh.clone(),
+            };
+            rows.push(("SSH", ssh, Some("ssh")));
+        }
+        if sources {
+            let pass = if self.pass.is_empty() { "" } else { "***" };
+            rows.push(("Password", pass.to_string(), Some("pass")));
+        }
+        let signed_in = if self.token.borrow().is_some() { "yes" } else { "no" };
+        rows.push(("Signed in", signed_in.to_string(), None));
+        rows.push(("Format", self.format.clone(), Some("format")));
+        rows.push(("Meta", if self.meta { "on" } else { "off" }.to_string(), None));
+        rows.push(("Pretty", self.pretty.to_string(), None));
+        rows.push(("Timeout", format!("{}s", self.timeout), Some("timeout")));
+        rows.push(("Connect timeout", format!("{}s", self.connect_timeout), Some("connect_timeout")));
+        let retried = if self.retry_unavailable { ", 502/503/504 too" } else { "" };
+        rows.push(("Retries", format!("{} (from {}ms{})", self.retries, self.retry_delay, retried), Some("retries")));
+        rows.push(("Suggestion", self.comple.to_string(), None));
+        rows.push(("Color", if self.color { "on" } else { "off" }.to_string(), None));
+        if !self.profile.is_empty() {
+            rows.push(("Profile", self.profile.clone(), None));
+        }
+        rows.push(("History", format!("{} ({})", self.history_size, self.history_scope), None));
+        let log = if self.log {
+            format!("on ({} entries, {} days, outputs up to {} bytes)", self.log_keep, self.log_days, self.log_output)
+        } else {
+            "off".to_string()
+        };
+        rows.push(("Log", log, None));
+        let vault = if crate::vault::is_unlocked() { "unlocked" } else { "locked" };
+        rows.push(("Vault", vault.to_string(), None));
+
         let mut table = Table::new();
         table
             .load_preset(UTF8_FULL)
-            .set_content_arrangement(ContentArrangement::Dynamic)
-            .set_header(vec!["VARIABLE", "VALUE"]);
-
-        table.add_row(vec!["Host", &self.host]);
-        table.add_row(vec!["User", &self.user]);
-        table.add_row(vec!["Namespace", &self.namespace]);
-        table.add_row(vec!["Database", &self.database]);
-        table.add_row(vec!["Schema", &self.schema]);
-        table.add_row(vec!["Pretty", &self.pretty.to_string()]);
-        table.add_row(vec!["Timeout", &format!("{}s", self.timeout)]);
-        table.add_row(vec!["Suggestion", &self.comple.to_string()]);
+            .set_content_arrangement(ContentArrangement::Dynamic);
+        if sources {
+            table.set_header(vec!["VARIABLE", "VALUE", "SOURCE"]);
+        } else {
+            table.set_header(vec!["VARIABLE", "VALUE"]);
+        }
+        for (name, value, key) in rows {
+            if sources {
+                let source = key.map_or("", |key| self.source(key));
+                table.add_row(vec![name, &value, source]);
+            } else {
+                table.add_row(vec![name, &value]);
+            }
+        }
 
         println!();
         println!("{}", table);
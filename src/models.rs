@@ -1,4 +1,9 @@
+use crate::rpc::RpcSession;
+use crate::schema::SchemaCache;
 use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 /// Main database connection configuration
 #[derive(Debug, Clone)]
@@ -7,12 +12,50 @@ pub struct SurrDB {
     pub namespace: String,
     pub database: String,
     pub user: String,
+    /// Kept for the whole session: tokens expire and WebSocket connections drop,
+    /// and signing in again must not stop a script to prompt or unlock the vault
     pub pass: String,
     pub schema: String,
     pub pretty: bool,
     pub timeout: u64,
+    /// Seconds allowed to open a connection, separate from the request timeout
+    pub connect_timeout: u64,
+    /// Extra attempts after a refused connection
+    pub retries: u32,
+    /// Also retry 502/503/504 answers, which the server may have acted on
+    pub retry_unavailable: bool,
+    /// Milliseconds before the first retry, doubled for each one after
+    pub retry_delay: u64,
+    /// HTTP client kept for the session so connections are reused
+    pub http: RefCell<Option<reqwest::blocking::Client>>,
     pub comple: usize,
     pub query: String,
+    pub transport: String,
+    pub rpc: Rc<RefCell<Option<RpcSession>>>,
+    pub auth: String,
+    pub access: String,
+    pub token: RefCell<Option<String>>,
+    pub schema_cache: Rc<RefCell<SchemaCache>>,
+    pub color: bool,
+    pub profile: String,
+    pub history_size: usize,
+    pub history_scope: String,
+    pub history_secrets: bool,
+    pub format: String,
+    pub meta: bool,
+    pub session_params: Rc<RefCell<serde_json::Map<String, serde_json::Value>>>,
+    pub log: bool,
+    pub log_output: usize,
+    pub log_keep: usize,
+    pub log_days: u64,
+    pub tls: TlsOptions,
+    /// Proxy URL (http, https, socks5, socks5h), `direct` for none, empty for the environment
+    pub proxy: String,
+    /// `[user@]bastion[:port]` to reach the host through an SSH local forward
+    pub ssh: String,
+    pub tunnel: Rc<RefCell<Option<crate::tunnel::Tunnel>>>,
+    /// Where each layered option got its value (flag, env, profile, config, ...)
+    pub sources: HashMap<String, String>,
 }
 
 impl Default for SurrDB {
@@ -26,8 +69,36 @@ impl Default for SurrDB {
             schema: "http".to_string(),
             pretty: true,
             timeout: 5,
+            connect_timeout: 5,
+            retries: 2,
+            retry_unavailable: false,
+            retry_delay: 200,
+            http: RefCell::new(None),
             comple: 5,
             query: String::new(),
+            transport: "http".to_string(),
+            rpc: Rc::new(RefCell::new(None)),
+            auth: "root".to_string(),
+            access: String::new(),
+            token: RefCell::new(None),
+            schema_cache: Rc::new(RefCell::new(SchemaCache::default())),
+            color: true,
+            profile: String::new(),
+            history_size: 1000,
+            history_scope: "global".to_string(),
+            history_secrets: false,
+            format: "json".to_string(),
+            meta: false,
+            session_params: Rc::new(RefCell::new(serde_json::Map::new())),
+            log: true,
+            log_output: 65536,
+            log_keep: 5000,
+            log_days: 90,
+            tls: TlsOptions::default(),
+            proxy: String::new(),
+            ssh: String::new(),
+            tunnel: Rc::new(RefCell::new(None)),
+            sources: HashMap::new(),
         }
     }
 }
@@ -50,13 +121,74 @@ pub struct Profile {
     pub dbuser: String,
     pub ns: String,
     pub db: String,
+    pub transport: String,
+    pub auth: String,
+    pub access: String,
+    pub vault: String,
+    pub is_default: bool,
+    pub tls: TlsOptions,
+    pub proxy: String,
+    pub ssh: String,
     pub date: String,
 }
 
+/// TLS settings of a connection; empty paths use the system defaults
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsOptions {
+    /// PEM bundle of extra trusted CA certificates
+    pub ca_cert: String,
+    /// PEM client certificate for mutual TLS
+    pub client_cert: String,
+    /// PKCS#8 PEM key of the client certificate
+    pub client_key: String,
+    /// Name checked against the server certificate instead of the host
+    pub server_name: String,
+    /// Skip certificate and hostname verification
+    pub insecure: bool,
+}
+
 /// Saved query stored in SQLite
 #[derive(Debug, Clone)]
 pub struct SavedQuery {
     pub qid: i32,
     pub idx: String,
     pub query: String,
+    pub params: String,
+    pub description: String,
+    /// Comma separated tags
+    pub tags: String,
+    pub created: String,
+    pub updated: String,
+    pub last_run: String,
+    pub runs: i64,
+}
+
+/// Key derivation salt and passphrase check of the vault
+#[derive(Debug, Clone)]
+pub struct VaultKey {
+    pub salt: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub verifier: Vec<u8>,
+}
+
+/// Encrypted secret stored in the vault
+#[derive(Debug, Clone)]
+pub struct VaultEntry {
+    pub idx: String,
+    pub nonce: Vec<u8>,
+    pub secret: Vec<u8>,
+}
+
+/// Executed statement recorded in the Sess table
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub sid: i64,
+    pub profile: String,
+    pub host: String,
+    pub ns: String,
+    pub db: String,
+    pub query: String,
+    pub date: String,
+    pub duration: i64,
+    pub status: String,
 }
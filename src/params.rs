@@ -0,0 +1,412 @@
+use crate::models::{Payload, SurrDB};
+use crate::surql::{tokenize, TokenKind};
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+/// Types a saved query parameter can declare
+pub const PARAM_TYPES: &[&str] = &["any", "string", "int", "float", "bool", "datetime", "json"];
+
+/// Variables set by SurrealDB itself, never asked for
+const RESERVED: &[&str] = &[
+    "access", "after", "auth", "before", "event", "input", "parent", "scope", "session", "this",
+    "token", "value",
+];
+
+/// Declared parameter of a saved query: `name[:type][=default]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamSpec {
+    pub name: String,
+    pub kind: String,
+    pub default: Option<String>,
+    /// False for names only found in the query text, which may be left unset
+    pub required: bool,
+}
+
+impl std::fmt::Display for ParamSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.kind != "any" {
+            write!(f, ":{}", self.kind)?;
+        }
+        if let Some(default) = &self.default {
+            write!(f, "={}", default)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse one `name[:type][=default]` declaration
+pub fn parse_spec(text: &str) -> Result<ParamSpec> {
+    let (head, default) = match text.split_once('=') {
+        Some((head, default)) => (head, Some(default.to_string())),
+        None => (text, None),
+    };
+    let (name, kind) = head.split_once(':').unwrap_or((head, "any"));
+    let name = name.trim_start_matches('$');
+
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(anyhow::anyhow!("Invalid parameter name: {}", text));
+    }
+    if !PARAM_TYPES.contains(&kind) {
+        return Err(anyhow::anyhow!(
+            "Invalid type for ${}: {}. Must be one of {}.",
+            name,
+            kind,
+            PARAM_TYPES.join(", ")
+        ));
+    }
+    if let Some(default) = &default {
+        convert(name, kind, default)?;
+    }
+
+    Ok(ParamSpec {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        default,
+        required: true,
+    })
+}
+
+/// Parse the space separated declarations stored with a saved query
+pub fn parse_specs(text: &str) -> Result<Vec<ParamSpec>> {
+    text.split_whitespace().map(parse_spec).collect()
+}
+
+/// Join declarations back into their stored form
+pub fn format_specs(specs: &[ParamSpec]) -> String {
+    specs.iter().map(ToString::to_string).collect::<Vec<_>>().join(" ")
+}
+
+/// `$names` a query reads without binding them itself: LET, DEFINE PARAM,
+/// FOR loops, closure `|$a|` and DEFINE FUNCTION arguments are skipped
+pub fn query_params(query: &str) -> Vec<String> {
+    let tokens: Vec<_> = tokenize(query)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .collect();
+
+    let mut assigned = Vec::new();
+    let mut names = Vec::new();
+    let mut in_closure = false;
+    // Parenthesis depth inside DEFINE FUNCTION arguments, None outside
+    let mut fn_args: Option<i32> = None;
+    let mut fn_pending = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| &tokens[p]);
+        match token.text {
+            "|" if token.kind == TokenKind::Punct => in_closure = !in_closure,
+            "(" if fn_pending => {
+                fn_pending = false;
+                fn_args = Some(0);
+            }
+            _ => {}
+        }
+        if token.is_word("FUNCTION") && prev.is_some_and(|p| p.is_word("DEFINE")) {
+            fn_pending = true;
+        }
+        if let Some(depth) = fn_args.as_mut() {
+            match token.text {
+                "(" => *depth += 1,
+                ")" => *depth -= 1,
+                _ => {}
+            }
+            if *depth == 0 {
+                fn_args = None;
+            }
+        }
+
+        if token.kind != TokenKind::Param {
+            continue;
+        }
+        let name = token.text.trim_start_matches('$').to_string();
+        let binder = prev.is_some_and(|p| {
+            p.is_word("LET")
+                || p.is_word("FOR")
+                || p.is_word("PARAM")
+                || (in_closure && (p.text == "|" || p.text == ","))
+        });
+        if binder || fn_args.is_some() {
+            assigned.push(name);
+        } else if !assigned.contains(&name)
+            && !names.contains(&name)
+            && !RESERVED.contains(&name.as_str())
+        {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Declarations for every parameter of a query: explicit ones first,
+/// then optional untyped ones found in the text
+pub fn merge_specs(query: &str, declared: Vec<ParamSpec>) -> Vec<ParamSpec> {
+    let mut specs = declared;
+    for name in query_params(query) {
+        if !specs.iter().any(|s| s.name == name) {
+            specs.push(ParamSpec {
+                name,
+                kind: "any".to_string(),
+                default: None,
+                required: false,
+            });
+        }
+    }
+    specs
+}
+
+/// Convert a textual value to the declared type
+pub fn convert(name: &str, kind: &str, raw: &str) -> Result<Value> {
+    let invalid = || anyhow::anyhow!("${} expects {}, got {}", name, kind, raw);
+    match kind {
+        "string" => Ok(Value::String(raw.to_string())),
+        "int" => raw.parse::<i64>().map(Value::from).map_err(|_| invalid()),
+        "float" => raw.parse::<f64>().map(Value::from).map_err(|_| invalid()),
+        "bool" => raw.parse::<bool>().map(Value::from).map_err(|_| invalid()),
+        "datetime" => {
+            // YYYY-MM-DD with an optional time part, checked by the server
+            let date = raw.get(..10).unwrap_or_default();
+            let valid = date.len() == 10
+                && date.char_indices().all(|(i, c)| match i {
+                    4 | 7 => c == '-',
+                    _ => c.is_ascii_digit(),
+                });
+            if valid {
+                Ok(Value::String(raw.to_string()))
+            } else {
+                Err(invalid())
+            }
+        }
+        "json" => serde_json::from_str(raw).map_err(|_| invalid()),
+        // Untyped values are read as JSON when they parse, text otherwise
+        _ => Ok(serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))),
+    }
+}
+
+/// Bind `name=value` arguments to the parameters. Parameters without a value
+/// or default are left to the session; only declared ones must then be set there.
+pub fn bind(specs: &[ParamSpec], args: &[&str], session: &Map<String, Value>) -> Result<Map<String, Value>> {
+    let mut given = Map::new();
+    for arg in args {
+        let (name, raw) = arg
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Parameters are given as name=value, got {}", arg))?;
+        let name = name.trim_start_matches('$');
+        let spec = specs.iter().find(|s| s.name == name).ok_or_else(|| {
+            anyhow::anyhow!("Unknown parameter ${}. Expected: {}", name, format_specs(specs))
+        })?;
+        given.insert(name.to_string(), convert(name, &spec.kind, raw)?);
+    }
+
+    let mut vars = Map::new();
+    for spec in specs {
+        let value = match (given.remove(&spec.name), &spec.default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => convert(&spec.name, &spec.kind, default)?,
+            (None, None) if !spec.required || session.contains_key(&spec.name) => continue,
+            (None, None) => return Err(anyhow::anyhow!("Missing value for ${}", spec.name)),
+        };
+        vars.insert(spec.name.clone(), value);
+    }
+    Ok(vars)
+}
+
+/// Check a parameter name typed after `.let` or `.unset`
+fn param_name(text: &str) -> Result<String> {
+    let name = text.trim().trim_start_matches('$');
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return Err(anyhow::anyhow!("Invalid parameter name: {}", text.trim()));
+    }
+    if RESERVED.contains(&name) {
+        return Err(anyhow::anyhow!("${} is set by SurrealDB", name));
+    }
+    Ok(name.to_string())
+}
+
+/// JSON type name shown by `.params`
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "int",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+impl SurrDB {
+    /// Value of a `.let`: JSON as is, anything else evaluated by the server
+    fn eval_value(&self, text: &str) -> Result<Value> {
+        if let Ok(value) = serde_json::from_str(text) {
+            return Ok(value);
+        }
+
+        let (body, status) = self.requester(&format!("RETURN {};", text))?;
+        if status != 200 {
+            return Err(crate::error::SurrError::from_status(status, &body).into());
+        }
+        let payload = serde_json::from_str::<Vec<Payload>>(&body)?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("No result for {}", text))?;
+        if payload.status != "OK" {
+            return Err(anyhow::anyhow!("Invalid value: {}", crate::render::cell_text(&payload.result)));
+        }
+        Ok(payload.result)
+    }
+
+    /// `.let name = value`: keep a parameter for every following query
+    pub fn let_param(&self, args: &str) -> Result<()> {
+        let (name, text) = args
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Usage: .let <name> = <value>"))?;
+        let name = param_name(name)?;
+        let value = self.eval_value(text.trim())?;
+
+        self.rpc_sync_param(&name, Some(&value))?;
+        crate::utils::print_suc(&format!("${} <- {}", name, value));
+        self.session_params.borrow_mut().insert(name, value);
+        Ok(())
+    }
+
+    /// `.unset name`: forget a session parameter
+    pub fn unset_param(&self, name: &str) -> Result<()> {
+        let name = param_name(name)?;
+        if self.session_params.borrow_mut().remove(&name).is_none() {
+            return Err(anyhow::anyhow!("${} is not set", name));
+        }
+        self.rpc_sync_param(&name, None)?;
+        crate::utils::print_suc(&format!("${} unset", name));
+        Ok(())
+    }
+
+    /// `.params`: list the session parameters with their types
+    pub fn show_params(&self) {
+        let params = self.session_params.borrow();
+        if params.is_empty() {
+            println!("No session parameters. Use .let <name> = <value>");
+            return;
+        }
+
+        let mut table = comfy_table::Table::new();
+        table.load_preset(comfy_table::presets::UTF8_FULL);
+        table.set_header(vec!["NAME", "TYPE", "VALUE"]);
+        for (name, value) in params.iter() {
+            table.add_row(vec![format!("${}", name), value_type(value).to_string(), value.to_string()]);
+        }
+
+        println!();
+        println!("{}", table);
+        println!();
+    }
+
+    /// Run a saved query with `name=value` arguments bound as variables
+    pub fn run_saved_query(&self, name: &str, args: &[&str]) -> Result<()> {
+        let saved = crate::db::db_get_query_by_idx(name)?;
+        let specs = merge_specs(&saved.query, parse_specs(&saved.params)?);
+        let vars = bind(&specs, args, &self.session_params.borrow())?;
+        crate::db::db_record_query_run(&saved.idx)?;
+        self.contact_surr_with(&saved.query, &vars)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn specs(text: &str) -> Vec<ParamSpec> {
+        parse_specs(text).unwrap()
+    }
+
+    #[test]
+    fn parses_declarations() {
+        let parsed = specs("$lim:int=10 name since:datetime");
+        assert_eq!(parsed[0].name, "lim");
+        assert_eq!(parsed[0].kind, "int");
+        assert_eq!(parsed[0].default.as_deref(), Some("10"));
+        assert_eq!(parsed[1].kind, "any");
+        assert!(parsed.iter().all(|s| s.required));
+        assert_eq!(format_specs(&parsed), "lim:int=10 name since:datetime");
+    }
+
+    #[test]
+    fn rejects_bad_declarations() {
+        assert!(parse_spec("lim:frobnicate").is_err());
+        assert!(parse_spec("lim:int=ten").is_err());
+        assert!(parse_spec("bad-name").is_err());
+        assert!(parse_spec("$").is_err());
+    }
+
+    #[test]
+    fn converts_typed_values() {
+        assert_eq!(convert("a", "int", "42").unwrap(), json!(42));
+        assert_eq!(convert("a", "float", "1.5").unwrap(), json!(1.5));
+        assert_eq!(convert("a", "bool", "true").unwrap(), json!(true));
+        assert_eq!(convert("a", "string", "42").unwrap(), json!("42"));
+        assert_eq!(convert("a", "json", "[1,2]").unwrap(), json!([1, 2]));
+        assert_eq!(convert("a", "any", "{\"x\":1}").unwrap(), json!({ "x": 1 }));
+        assert_eq!(convert("a", "any", "plain text").unwrap(), json!("plain text"));
+        assert_eq!(convert("a", "datetime", "2024-01-31T10:00:00Z").unwrap(), json!("2024-01-31T10:00:00Z"));
+        assert!(convert("a", "datetime", "31/01/2024").is_err());
+        assert!(convert("a", "int", "1.5").is_err());
+    }
+
+    #[test]
+    fn finds_params_read_by_the_query() {
+        let query = "SELECT * FROM person WHERE age > $min AND name = $name AND $auth.id = owner LIMIT $min";
+        assert_eq!(query_params(query), vec!["min", "name"]);
+    }
+
+    #[test]
+    fn skips_names_the_query_binds_itself() {
+        let query = "LET $x = 1;\n\
+                     DEFINE PARAM $limit VALUE 5;\n\
+                     FOR $p IN $people { CREATE log SET p = $p, x = $x };\n\
+                     RETURN array::map($list, |$a, $b| $a + $b + $offset);\n\
+                     DEFINE FUNCTION fn::add($q: int, $r: int) { RETURN $q + $r + $base; };";
+        assert_eq!(query_params(query), vec!["people", "list", "offset", "base"]);
+    }
+
+    #[test]
+    fn found_params_are_optional() {
+        let merged = merge_specs("SELECT * FROM a WHERE x = $x AND y = $y", specs("x:int"));
+        assert_eq!(merged.len(), 2);
+        assert!(merged[0].required);
+        assert_eq!(merged[1].name, "y");
+        assert!(!merged[1].required);
+    }
+
+    #[test]
+    fn binds_arguments_and_defaults() {
+        let declared = specs("lim:int=10 name:string");
+        let vars = bind(&declared, &["name=ann"], &Map::new()).unwrap();
+        assert_eq!(vars["lim"], json!(10));
+        assert_eq!(vars["name"], json!("ann"));
+
+        let vars = bind(&declared, &["$lim=3", "name=7"], &Map::new()).unwrap();
+        assert_eq!(vars["lim"], json!(3));
+        assert_eq!(vars["name"], json!("7"));
+    }
+
+    #[test]
+    fn bind_rejects_bad_arguments() {
+        let declared = specs("lim:int");
+        assert!(bind(&declared, &["lim=x"], &Map::new()).is_err());
+        assert!(bind(&declared, &["other=1"], &Map::new()).is_err());
+        assert!(bind(&declared, &["lim"], &Map::new()).is_err());
+    }
+
+    #[test]
+    fn declared_params_fall_back_to_the_session() {
+        let declared = merge_specs("SELECT * FROM a LIMIT $lim START $start", specs("lim:int"));
+        let missing = bind(&declared, &[], &Map::new()).unwrap_err();
+        assert_eq!(missing.to_string(), "Missing value for $lim");
+
+        let mut session = Map::new();
+        session.insert("lim".to_string(), json!(4));
+        // Left out so the session value is used; $start is optional
+        assert!(bind(&declared, &[], &session).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,340 @@
+use crate::models::{Payload, SurrDB};
+use crate::utils::print_err;
+use anyhow::Result;
+use colored::Colorize;
+use comfy_table::{presets::UTF8_FULL, ContentArrangement, Table};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Cells longer than this are truncated in table output
+const MAX_CELL_WIDTH: usize = 40;
+
+/// Flatten nested objects into dotted column names
+pub fn flatten(value: &Value, prefix: &str, out: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, inner) in map {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(inner, &name, out);
+            }
+        }
+        _ => out.push((prefix.to_string(), value.clone())),
+    }
+}
+
+/// Text shown for a single value
+pub fn cell_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "NULL".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Output formats accepted by `--format` and `.set format`
+pub const FORMATS: &[&str] = &["json", "table", "raw", "csv", "ndjson", "yaml", "markdown"];
+
+/// Rows of a statement result with the sorted union of their columns
+pub fn rows_and_columns(result: &Value) -> (Vec<String>, Vec<Vec<(String, Value)>>) {
+    let items: Vec<&Value> = match result {
+        Value::Array(items) => items.iter().collect(),
+        Value::Null => Vec::new(),
+        other => vec![other],
+    };
+
+    let mut columns = BTreeSet::new();
+    let mut rows = Vec::with_capacity(items.len());
+    for item in items {
+        let mut row = Vec::new();
+        if item.is_object() {
+            flatten(item, "", &mut row);
+        } else {
+            row.push(("result".to_string(), item.clone()));
+        }
+        columns.extend(row.iter().map(|(key, _)| key.clone()));
+        rows.push(row);
+    }
+
+    // Sorted columns keep CSV output stable between runs
+    (columns.into_iter().collect(), rows)
+}
+
+pub fn truncate(text: &str) -> String {
+    if text.chars().count() > MAX_CELL_WIDTH {
+        let cut: String = text.chars().take(MAX_CELL_WIDTH - 1).collect();
+        format!("{}…", cut)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Render one statement result as a table
+fn statement_table(result: &Value) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic);
+
+    // A single object (INFO FOR ...) reads better as key/value pairs
+    if let Value::Object(_) = result {
+        let mut pairs = Vec::new();
+        flatten(result, "", &mut pairs);
+        table.set_header(vec!["KEY", "VALUE"]);
+        for (key, value) in pairs {
+            table.add_row(vec![key, truncate(&cell_text(&value))]);
+        }
+        return table;
+    }
+
+    let (columns, rows) = rows_and_columns(result);
+    table.set_header(columns.iter().map(|c| c.to_uppercase()));
+    for row in rows {
+        table.add_row(columns.iter().map(|column| {
+            row.iter()
+                .find(|(key, _)| key == column)
+                .map(|(_, value)| truncate(&cell_text(value)))
+                .unwrap_or_default()
+        }));
+    }
+    table
+}
+
+/// Print a /sql response as one table per statement
+pub fn render_table(body: &str) -> Result<()> {
+    let Ok(payloads) = serde_json::from_str::<Vec<Payload>>(body) else {
+        // Not a statement list (HTTP error body), show it as JSON
+        return crate::utils::pretty_print(body);
+    };
+
+    for payload in payloads {
+        println!();
+        if payload.status == "OK" {
+            let (columns, _) = rows_and_columns(&payload.result);
+            if columns.is_empty() {
+                println!("(empty)");
+            } else {
+                println!("{}", statement_table(&payload.result));
+            }
+        } else {
+            print_err(&format!("Statement failed: {}", cell_text(&payload.result)));
+        }
+
+        let status = if payload.status == "OK" {
+            payload.status.green()
+        } else {
+            payload.status.red()
+        };
+        println!("{} {}  {} {}", "time:".bright_black(), payload.time, "status:".bright_black(), status);
+    }
+    println!();
+
+    Ok(())
+}
+
+/// Report the failure of the statement at index `i` on stderr
+fn report_statement_error(i: usize, payload: &Payload) {
+    crate::utils::eprint_err(&format!("Statement {} failed: {}", i + 1, cell_text(&payload.result)));
+}
+
+/// Repeat the message of every failed statement below JSON output, on stderr
+/// so the JSON on stdout stays parseable
+fn report_failed(body: &str) {
+    let Ok(payloads) = serde_json::from_str::<Vec<Payload>>(body) else {
+        return;
+    };
+    for (i, payload) in payloads.iter().enumerate() {
+        if payload.status != "OK" {
+            report_statement_error(i, payload);
+        }
+    }
+}
+
+/// Result rows of all statements, wrapped with statement metadata when asked.
+/// Failed statements are reported on stderr and contribute no rows.
+fn result_rows(payloads: &[Payload], meta: bool) -> Vec<Value> {
+    let mut rows = Vec::new();
+    for (i, payload) in payloads.iter().enumerate() {
+        if payload.status != "OK" {
+            report_statement_error(i, payload);
+            continue;
+        }
+
+        let items: Vec<Value> = match &payload.result {
+            Value::Array(items) => items.clone(),
+            Value::Null => Vec::new(),
+            other => vec![other.clone()],
+        };
+        for item in items {
+            if meta {
+                rows.push(serde_json::json!({
+                    "statement": i + 1,
+                    "time": payload.time,
+                    "status": payload.status,
+                    "result": item,
+                }));
+            } else {
+                rows.push(item);
+            }
+        }
+    }
+    rows
+}
+
+/// Print rows as CSV on stdout
+fn render_csv(rows: &[Value]) -> Result<()> {
+    write_csv(rows, std::io::stdout())
+}
+
+/// Write rows as CSV with a header of the sorted, flattened columns
+fn write_csv(rows: &[Value], out: impl std::io::Write) -> Result<()> {
+    let (columns, rows) = rows_and_columns(&Value::Array(rows.to_vec()));
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(&columns)?;
+    for row in rows {
+        writer.write_record(columns.iter().map(|column| {
+            row.iter()
+                .find(|(key, _)| key == column)
+                .map(|(_, value)| match value {
+                    Value::Null => String::new(),
+                    other => cell_text(other),
+                })
+                .unwrap_or_default()
+        }))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Print one JSON document per row
+fn render_ndjson(rows: &[Value]) -> Result<()> {
+    for row in rows {
+        println!("{}", serde_json::to_string(row)?);
+    }
+    Ok(())
+}
+
+/// Print rows as a YAML sequence
+fn render_yaml(rows: &[Value]) -> Result<()> {
+    print!("{}", serde_yaml::to_string(rows)?);
+    Ok(())
+}
+
+/// Escape a cell for a Markdown table
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// Print one Markdown table per statement
+fn render_markdown(payloads: &[Payload], meta: bool) {
+    for (i, payload) in payloads.iter().enumerate() {
+        if payload.status != "OK" {
+            report_statement_error(i, payload);
+            continue;
+        }
+
+        let (columns, rows) = rows_and_columns(&payload.result);
+        if !columns.is_empty() {
+            println!("| {} |", columns.iter().map(|c| markdown_cell(c)).collect::<Vec<_>>().join(" | "));
+            println!("|{}", " --- |".repeat(columns.len()));
+            for row in rows {
+                let cells: Vec<String> = columns
+                    .iter()
+                    .map(|column| {
+                        row.iter()
+                            .find(|(key, _)| key == column)
+                            .map(|(_, value)| markdown_cell(&cell_text(value)))
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                println!("| {} |", cells.join(" | "));
+            }
+        }
+        if meta {
+            println!("\n_statement {}: {} in {}_", i + 1, payload.status, payload.time);
+        }
+        println!();
+    }
+}
+
+impl SurrDB {
+    /// Print a /sql response in the configured output format
+    pub fn print_response(&self, body: &str) -> Result<()> {
+        match self.format.as_str() {
+            "json" | "raw" => {
+                if self.format == "json" && self.pretty {
+                    crate::utils::pretty_print(body)?;
+                } else {
+                    crate::utils::print_raw(body);
+                }
+                report_failed(body);
+                return Ok(());
+            }
+            "table" => return render_table(body),
+            _ => {}
+        }
+
+        let Ok(payloads) = serde_json::from_str::<Vec<Payload>>(body) else {
+            // Not a statement list (HTTP error body), show it as JSON
+            return crate::utils::pretty_print(body);
+        };
+
+        match self.format.as_str() {
+            "csv" => render_csv(&result_rows(&payloads, self.meta)),
+            "ndjson" => render_ndjson(&result_rows(&payloads, self.meta)),
+            "yaml" => render_yaml(&result_rows(&payloads, self.meta)),
+            _ => {
+                render_markdown(&payloads, self.meta);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn csv(rows: Value) -> String {
+        let mut out = Vec::new();
+        write_csv(rows.as_array().unwrap(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn columns_are_the_sorted_union_of_all_rows() {
+        let (columns, rows) = rows_and_columns(&json!([
+            { "name": "Tobie", "address": { "city": "London" } },
+            { "age": 30, "name": "Jaime" },
+            7,
+        ]));
+        assert_eq!(columns, ["address.city", "age", "name", "result"]);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2], [("result".to_string(), json!(7))]);
+    }
+
+    #[test]
+    fn csv_quotes_separators_and_leaves_missing_cells_empty() {
+        let out = csv(json!([
+            { "name": "Smith, John", "note": "says \"hi\"" },
+            { "name": "Two\nlines", "age": 30, "tags": ["a", "b"] },
+        ]));
+        assert_eq!(
+            out,
+            "age,name,note,tags\n\
+             ,\"Smith, John\",\"says \"\"hi\"\"\",\n\
+             30,\"Two\nlines\",,\"[\"\"a\"\",\"\"b\"\"]\"\n"
+        );
+    }
+
+    #[test]
+    fn markdown_cells_escape_pipes_and_newlines() {
+        assert_eq!(markdown_cell("a|b"), "a\\|b");
+        assert_eq!(markdown_cell("one\ntwo"), "one<br>two");
+        assert_eq!(markdown_cell("plain"), "plain");
+    }
+}
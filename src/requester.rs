@@ -1,66 +1,285 @@
-use crate::models::SurrDB;
+use crate::error::SurrError;
+use crate::models::{Payload, SurrDB};
 use anyhow::Result;
+use serde_json::{Map, Value};
 
 impl SurrDB {
-    /// Generate Basic Auth header value
-    pub fn basic_auth(&self) -> String {
-        let auth = format!("{}:{}", self.user, self.pass);
-        use base64::prelude::*;
-        BASE64_STANDARD.encode(auth)
+    /// Send SQL query to SurrealDB over the selected transport
+    pub fn requester(&self, query: &str) -> Result<(String, u16)> {
+        self.requester_with(query, &Map::new())
     }
 
-    /// Send SQL query to SurrealDB
-    pub fn requester(&self, query: &str) -> Result<(String, u16)> {
-        let url = format!("{}://{}/sql", self.schema, self.host);
+    /// Send SQL query with bound `$variables`
+    pub fn requester_with(&self, query: &str, vars: &Map<String, Value>) -> Result<(String, u16)> {
+        match self.transport.as_str() {
+            "ws" => self.rpc_requester(query, vars),
+            _ => self.http_requester(query, vars),
+        }
+    }
+
+    /// Send SQL query to the HTTP `/sql` endpoint
+    pub fn http_requester(&self, query: &str, vars: &Map<String, Value>) -> Result<(String, u16)> {
+        let token = match self.bearer()? {
+            Ok(token) => token,
+            Err(failed) => return Ok(failed),
+        };
+
+        let (body, status) = self.http_send(query, vars, &token)?;
+
+        // The server rejected the token before it expired, sign in once more
+        if status == 401 || status == 403 {
+            self.reset_token();
+            return match self.bearer()? {
+                Ok(token) => self.http_send(query, vars, &token),
+                Err(failed) => Ok(failed),
+            };
+        }
+
+        Ok((body, status))
+    }
 
-        let client = reqwest::blocking::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout))
-            .build()?;
+    /// POST the query with a bearer token. Session and query variables are
+    /// sent as `LET` statements in front of it so they keep their JSON type.
+    fn http_send(&self, query: &str, vars: &Map<String, Value>, token: &str) -> Result<(String, u16)> {
+        let url = format!("{}://{}/sql", self.schema, self.authority()?);
+        let all_vars = merged_vars(&self.session_params.borrow(), vars);
+        let body = typed_body(query, &all_vars);
 
-        let response = client
-            .post(&url)
-            .header("Authorization", format!("Basic {}", self.basic_auth()))
-            .header("NS", &self.namespace)
-            .header("DB", &self.database)
-            .header("Accept", "application/json")
-            .body(query.to_string())
-            .send()?;
+        let response = self.send(|client| {
+            client
+                .post(&url)
+                .header("Authorization", format!("Bearer {}", token))
+                .header("NS", &self.namespace)
+                .header("DB", &self.database)
+                .header("Accept", "application/json")
+                .body(body.clone())
+        })?;
 
         let status = response.status().as_u16();
         let body = response.text()?;
+        if status != 200 {
+            return Ok((body, status));
+        }
 
-        Ok((body, status))
+        Ok((skip_results(&body, all_vars.len())?, status))
     }
 
-    /// Execute query and print result
+    /// Execute query and print result.
+    /// Failures are reported here and returned as a [`SurrError`] for the exit code.
     pub fn contact_surr(&self, query: &str) -> Result<()> {
-        let (resp, _) = self.requester(query)?;
+        self.contact_surr_with(query, &Map::new())
+    }
 
-        if self.pretty {
-            crate::utils::pretty_print(&resp)?;
+    /// Execute query with bound `$variables` and print result
+    pub fn contact_surr_with(&self, query: &str, vars: &Map<String, Value>) -> Result<()> {
+        self.contact_surr_after(&[], query, vars, &[]).map(|_| ())
+    }
+
+    /// Execute query after `context` statements whose results are left out, and
+    /// return the values the query left in the `capture` parameters.
+    /// Scripts use this to carry USE and LET over stateless HTTP requests.
+    pub fn contact_surr_after(
+        &self,
+        context: &[String],
+        query: &str,
+        vars: &Map<String, Value>,
+        capture: &[String],
+    ) -> Result<Map<String, Value>> {
+        let started = std::time::Instant::now();
+        let mut request = if context.is_empty() {
+            query.to_string()
         } else {
-            crate::utils::print_raw(&resp);
+            format!("{}\n{}", context.join("\n"), query)
+        };
+        if !capture.is_empty() {
+            request.push_str(&capture_statement(capture));
         }
+        let (mut resp, status) = match self.requester_with(&request, vars) {
+            Ok(response) => response,
+            Err(e) => {
+                self.log_execution(query, started, "CONN", None);
+                return Err(report(SurrError::Connection(format!("{:#}", e))));
+            }
+        };
 
-        Ok(())
+        if status != 200 {
+            self.log_execution(query, started, &format!("HTTP {}", status), Some(&resp));
+            return Err(report(SurrError::from_status(status, &resp)));
+        }
+
+        resp = skip_results(&resp, context.len())?;
+        let captured = if capture.is_empty() {
+            Map::new()
+        } else {
+            let (rest, captured) = split_captured(&resp)?;
+            resp = rest;
+            captured
+        };
+
+        let failed = serde_json::from_str::<Vec<Payload>>(&resp)
+            .map(|payloads| payloads.iter().filter(|p| p.status != "OK").count())
+            .unwrap_or_default();
+        self.log_execution(query, started, if failed > 0 { "ERR" } else { "OK" }, Some(&resp));
+
+        self.print_response(&resp)?;
+        if failed > 0 {
+            return Err(SurrError::Statement(failed).into());
+        }
+
+        Ok(captured)
     }
 
     /// Test connection to SurrealDB
     pub fn test_connection(&self) -> Result<()> {
-        let (_, status) = self.requester("INFO FOR DB;")?;
+        let status = match self.requester("INFO FOR DB;") {
+            Ok((_, status)) => status,
+            Err(e) => {
+                crate::utils::print_err(&format!(
+                    "Cannot connect to {}: {:#}\nUse .set host <address> to change the address.",
+                    self.host, e
+                ));
+                return Ok(());
+            }
+        };
 
         match status {
             200 => crate::utils::print_suc("Connection is OK!"),
-            403 => {
+            401 | 403 => {
                 crate::utils::print_err(
                     "There was a problem with authentication.\nUse .set user <username> to reset credentials."
                 );
             }
             _ => {
-                crate::utils::print_err("Error!");
+                crate::utils::print_err(&format!("Error! Server answered HTTP {}.", status));
             }
         }
 
         Ok(())
     }
 }
+
+/// Session parameters with the query's own variables laid over them
+fn merged_vars(session: &Map<String, Value>, vars: &Map<String, Value>) -> Map<String, Value> {
+    let mut all_vars = session.clone();
+    all_vars.extend(vars.clone());
+    all_vars
+}
+
+/// Query preceded by one `LET $name = <JSON value>;` per variable. The `/sql`
+/// endpoint reads URL variables as strings, JSON literals keep numbers,
+/// booleans, arrays and objects typed.
+fn typed_body(query: &str, vars: &Map<String, Value>) -> String {
+    let mut body: String = vars
+        .iter()
+        .map(|(name, value)| format!("LET ${} = {};\n", name, value))
+        .collect();
+    body.push_str(query);
+    body
+}
+
+/// Leave out the results of the first `count` statements of a response
+fn skip_results(body: &str, count: usize) -> Result<String> {
+    if count == 0 {
+        return Ok(body.to_string());
+    }
+    match serde_json::from_str::<Vec<Value>>(body) {
+        Ok(results) => Ok(serde_json::to_string(&results[count.min(results.len())..])?),
+        Err(_) => Ok(body.to_string()),
+    }
+}
+
+/// Statement returning the current value of every parameter in `names` as one object
+fn capture_statement(names: &[String]) -> String {
+    let fields: Vec<String> = names
+        .iter()
+        .map(|name| format!("{}: ${}", Value::String(name.clone()), name))
+        .collect();
+    format!("\nRETURN {{ {} }};", fields.join(", "))
+}
+
+/// Split the result of the capture statement off the end of a response
+fn split_captured(body: &str) -> Result<(String, Map<String, Value>)> {
+    let mut results: Vec<Value> = serde_json::from_str(body)?;
+    let captured = match results.pop() {
+        Some(Value::Object(mut last)) => match last.remove("result") {
+            Some(Value::Object(values)) => values,
+            _ => Map::new(),
+        },
+        _ => Map::new(),
+    };
+    Ok((serde_json::to_string(&results)?, captured))
+}
+
+/// Print a failure and convert it for propagation
+fn report(err: SurrError) -> anyhow::Error {
+    crate::utils::eprint_err(&err.to_string());
+    err.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn typed_variables_reach_the_body_as_json() {
+        let mut vars = Map::new();
+        vars.insert("limit".to_string(), json!(10));
+        vars.insert("active".to_string(), json!(true));
+        vars.insert("name".to_string(), json!("a \"quoted\" name"));
+        vars.insert("filter".to_string(), json!({ "age": 30 }));
+
+        let body = typed_body("SELECT * FROM person LIMIT $limit;", &vars);
+        assert_eq!(
+            body,
+            "LET $active = true;\n\
+             LET $filter = {\"age\":30};\n\
+             LET $limit = 10;\n\
+             LET $name = \"a \\\"quoted\\\" name\";\n\
+             SELECT * FROM person LIMIT $limit;"
+        );
+    }
+
+    #[test]
+    fn session_parameters_are_sent_typed_under_query_variables() {
+        let mut session = Map::new();
+        session.insert("since".to_string(), json!({ "days": 7 }));
+        session.insert("limit".to_string(), json!(5));
+        let mut vars = Map::new();
+        vars.insert("limit".to_string(), json!(20));
+
+        let body = typed_body("RETURN $limit;", &merged_vars(&session, &vars));
+        assert_eq!(body, "LET $limit = 20;\nLET $since = {\"days\":7};\nRETURN $limit;");
+    }
+
+    #[test]
+    fn body_without_variables_is_the_query() {
+        assert_eq!(typed_body("RETURN 1;", &Map::new()), "RETURN 1;");
+    }
+
+    #[test]
+    fn skips_the_results_of_the_let_statements() {
+        let body = r#"[{"status":"OK","result":null},{"status":"OK","result":null},{"status":"OK","result":[1]}]"#;
+        let kept: Vec<Value> = serde_json::from_str(&skip_results(body, 2).unwrap()).unwrap();
+        assert_eq!(kept, vec![json!({ "status": "OK", "result": [1] })]);
+
+        assert_eq!(skip_results("not json", 2).unwrap(), "not json");
+        assert_eq!(skip_results(body, 0).unwrap(), body);
+    }
+
+    #[test]
+    fn captured_values_are_split_off_the_response() {
+        let names = ["lim".to_string(), "p".to_string()];
+        assert_eq!(capture_statement(&names), "\nRETURN { \"lim\": $lim, \"p\": $p };");
+
+        let body = json!([
+            { "time": "1ms", "status": "OK", "result": [{ "id": "person:1" }] },
+            { "time": "1ms", "status": "OK", "result": { "lim": 5, "p": [{ "id": "person:1" }] } },
+        ])
+        .to_string();
+        let (rest, captured) = split_captured(&body).unwrap();
+        assert_eq!(rest, json!([{ "time": "1ms", "status": "OK", "result": [{ "id": "person:1" }] }]).to_string());
+        assert_eq!(captured["lim"], json!(5));
+        assert_eq!(captured["p"], json!([{ "id": "person:1" }]));
+    }
+}
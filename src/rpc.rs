@@ -0,0 +1,340 @@
+use crate::models::SurrDB;
+use crate::utils::{print_err, print_suc};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Connector, Message, WebSocket};
+
+/// How often a live query watch checks whether it should stop
+const WATCH_POLL: Duration = Duration::from_millis(250);
+
+/// Error returned by the SurrealDB `/rpc` endpoint
+#[derive(Debug, thiserror::Error)]
+#[error("RPC error {code}: {message}")]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// Persistent WebSocket session speaking SurrealDB's JSON-RPC protocol
+#[derive(Debug)]
+pub struct RpcSession {
+    socket: WebSocket<MaybeTlsStream<TcpStream>>,
+    next_id: u64,
+}
+
+impl RpcSession {
+    /// Open a WebSocket connection to `<ws|wss>://host/rpc`
+    pub fn connect(surr: &SurrDB) -> Result<Self> {
+        let schema = if surr.schema == "https" { "wss" } else { "ws" };
+        let url = format!("{}://{}/rpc", schema, surr.authority()?);
+        let timeout = Duration::from_secs(surr.timeout);
+
+        let stream = surr.open_stream()?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+
+        let mut request = url.into_client_request()?;
+        request
+            .headers_mut()
+            .insert("Sec-WebSocket-Protocol", "json".parse()?);
+
+        let connector = match schema {
+            "wss" => Connector::NativeTls(surr.tls_connector()?),
+            _ => Connector::Plain,
+        };
+        let (socket, _) = tungstenite::client_tls_with_config(request, stream, None, Some(connector))
+            .map_err(|e| anyhow::anyhow!("WebSocket handshake failed: {}", e))?;
+
+        Ok(Self { socket, next_id: 1 })
+    }
+
+    /// Send a method call and wait for the response with the matching id
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = json!({ "id": id.to_string(), "method": method, "params": params });
+        self.socket.send(Message::Text(request.to_string()))?;
+
+        loop {
+            let text = match self.socket.read()? {
+                Message::Text(text) => text,
+                Message::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+                Message::Close(_) => return Err(anyhow::anyhow!("Connection closed by server")),
+                _ => continue,
+            };
+
+            let response: Value = serde_json::from_str(&text)?;
+            if response["id"].as_str() != Some(id.to_string().as_str()) {
+                print_notification(&response);
+                continue;
+            }
+
+            if let Some(err) = response.get("error") {
+                return Err(RpcError {
+                    code: err["code"].as_i64().unwrap_or_default(),
+                    message: err["message"].as_str().unwrap_or_default().to_string(),
+                }
+                .into());
+            }
+
+            return Ok(response["result"].clone());
+        }
+    }
+
+    /// Switch namespace and database for the session
+    pub fn use_ns(&mut self, ns: &str, db: &str) -> Result<Value> {
+        self.call("use", json!([ns, db]))
+    }
+
+    /// Authenticate the session
+    pub fn signin(&mut self, credentials: Value) -> Result<Value> {
+        self.call("signin", json!([credentials]))
+    }
+
+    /// Run SurrealQL statements with optional variables
+    pub fn query(&mut self, query: &str, vars: Value) -> Result<Value> {
+        self.call("query", json!([query, vars]))
+    }
+
+    /// Define a session parameter
+    pub fn let_var(&mut self, name: &str, value: Value) -> Result<Value> {
+        self.call("let", json!([name, value]))
+    }
+
+    /// Remove a session parameter
+    pub fn unset(&mut self, name: &str) -> Result<Value> {
+        self.call("unset", json!([name]))
+    }
+
+    /// Start a live query on a table, returning its id
+    pub fn live(&mut self, table: &str) -> Result<Value> {
+        self.call("live", json!([table]))
+    }
+
+    /// Stop a live query
+    pub fn kill(&mut self, id: &str) -> Result<Value> {
+        self.call("kill", json!([id]))
+    }
+
+    /// Change how long a read waits on the underlying TCP stream
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        match self.socket.get_ref() {
+            MaybeTlsStream::Plain(stream) => stream.set_read_timeout(timeout)?,
+            MaybeTlsStream::NativeTls(stream) => stream.get_ref().set_read_timeout(timeout)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Print live query notifications until `stop` is set
+    pub fn watch(&mut self, stop: &AtomicBool, timeout: Duration) -> Result<()> {
+        self.set_read_timeout(Some(WATCH_POLL))?;
+        let result = loop {
+            if stop.load(Ordering::Relaxed) {
+                break Ok(());
+            }
+            let text = match self.socket.read() {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Binary(bytes)) => String::from_utf8_lossy(&bytes).into_owned(),
+                Ok(Message::Close(_)) => break Err(anyhow::anyhow!("Connection closed by server")),
+                Ok(_) => continue,
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    continue
+                }
+                Err(e) => break Err(e.into()),
+            };
+            match serde_json::from_str(&text) {
+                Ok(message) => print_notification(&message),
+                Err(e) => break Err(e.into()),
+            }
+        };
+        self.set_read_timeout(Some(timeout))?;
+        result
+    }
+}
+
+/// Print a live query notification received between responses
+fn print_notification(message: &Value) {
+    let result = &message["result"];
+    let action = result["action"].as_str().unwrap_or("NOTIFY");
+    println!(
+        "[LIVE {}] {}",
+        action,
+        serde_json::to_string(&result["result"]).unwrap_or_default()
+    );
+}
+
+impl SurrDB {
+    /// Open a signed-in WebSocket session on the current namespace and database
+    fn rpc_open(&self) -> Result<RpcSession> {
+        let mut session = RpcSession::connect(self)?;
+        self.rpc_signin(&mut session)?;
+        session.use_ns(&self.namespace, &self.database)?;
+        // Session parameters live on the server connection, set them again
+        for (name, value) in self.session_params.borrow().iter() {
+            session.let_var(name, value.clone())?;
+        }
+        Ok(session)
+    }
+
+    /// Send SQL query over the WebSocket session, opening it on first use
+    pub fn rpc_requester(&self, query: &str, vars: &serde_json::Map<String, Value>) -> Result<(String, u16)> {
+        let mut slot = self.rpc.borrow_mut();
+        if slot.is_none() {
+            match self.rpc_open() {
+                Ok(session) => *slot = Some(session),
+                Err(e) if e.is::<RpcError>() => return Ok(rpc_error_body(e)),
+                Err(e) => return Err(e),
+            }
+        }
+
+        let session = slot.as_mut().expect("session opened above");
+        let vars = Value::Object(vars.clone());
+        let mut result = session.query(query, vars.clone());
+
+        // The session token expired, sign in again and retry once
+        if matches!(&result, Err(e) if is_auth_error(e)) {
+            if let Err(e) = self.rpc_signin(session) {
+                return Ok(rpc_error_body(e));
+            }
+            result = session.query(query, vars);
+        }
+
+        match result {
+            Ok(result) => Ok((result.to_string(), 200)),
+            Err(e) if e.is::<RpcError>() => Ok(rpc_error_body(e)),
+            Err(e) => {
+                // The socket is unusable, reconnect on the next statement
+                *slot = None;
+                Err(e)
+            }
+        }
+    }
+
+    /// Authenticate the WebSocket session and keep the returned token
+    fn rpc_signin(&self, session: &mut RpcSession) -> Result<()> {
+        let result = session.signin(self.signin_params())?;
+        // SurrealDB 1.x returns the token itself, 2.x may wrap it
+        let token = result.as_str().or(result["token"].as_str());
+        *self.token.borrow_mut() = token.map(str::to_string);
+        Ok(())
+    }
+
+    /// Apply a `.let` or `.unset` to an open WebSocket session
+    pub fn rpc_sync_param(&self, name: &str, value: Option<&Value>) -> Result<()> {
+        let mut slot = self.rpc.borrow_mut();
+        let Some(session) = slot.as_mut() else {
+            // Set on the next connection
+            return Ok(());
+        };
+        match value {
+            Some(value) => session.let_var(name, value.clone())?,
+            None => session.unset(name)?,
+        };
+        Ok(())
+    }
+
+    /// Drop the WebSocket session so the next statement reconnects
+    pub fn reset_rpc(&self) {
+        self.rpc.borrow_mut().take();
+    }
+
+    /// `.live [table]`: start a live query on a table, then print the notifications
+    /// of every live query of the session until Enter is pressed
+    pub fn live_query(&self, table: Option<&str>) {
+        if self.transport != "ws" {
+            print_err("Live queries need the WebSocket transport. Use .set transport ws.");
+            return;
+        }
+        let mut slot = self.rpc.borrow_mut();
+        if slot.is_none() {
+            match self.rpc_open() {
+                Ok(session) => *slot = Some(session),
+                Err(e) => {
+                    print_err(&format!("Cannot open the WebSocket session: {}", e));
+                    return;
+                }
+            }
+        }
+        let session = slot.as_mut().expect("session opened above");
+
+        if let Some(table) = table {
+            match session.live(table) {
+                Ok(id) => print_suc(&format!("Live query {} on {}", id.as_str().unwrap_or(&id.to_string()), table)),
+                Err(e) => {
+                    print_err(&format!("Cannot start live query: {}", e));
+                    return;
+                }
+            }
+        }
+        // Scripts only start the query, notifications show up between statements
+        if !atty::is(atty::Stream::Stdin) {
+            return;
+        }
+
+        println!("Watching live queries. Press Enter to stop.");
+        let stop = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut line = String::new();
+                let _ = std::io::stdin().read_line(&mut line);
+                stop.store(true, Ordering::Relaxed);
+            })
+        };
+
+        let result = session.watch(&stop, Duration::from_secs(self.timeout));
+        if let Err(e) = result {
+            // The socket is unusable, reconnect on the next statement
+            *slot = None;
+            print_err(&format!("Live query watch stopped: {}. Press Enter.", e));
+        }
+        let _ = reader.join();
+    }
+
+    /// `.kill <id>`: stop a live query of the session
+    pub fn kill_live(&self, id: &str) {
+        let mut slot = self.rpc.borrow_mut();
+        let Some(session) = slot.as_mut() else {
+            print_err("No live query is running: live queries end with the WebSocket session.");
+            return;
+        };
+        match session.kill(id) {
+            Ok(_) => print_suc(&format!("Live query {} stopped.", id)),
+            Err(e) => print_err(&format!("Cannot stop live query {}: {}", id, e)),
+        }
+    }
+}
+
+/// Check whether an RPC call failed because of authentication
+fn is_auth_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<RpcError>().is_some_and(|err| {
+        let message = err.message.to_lowercase();
+        message.contains("authentication") || message.contains("expired")
+    })
+}
+
+/// Convert an RPC error into an HTTP-like body and status
+fn rpc_error_body(e: anyhow::Error) -> (String, u16) {
+    match e.downcast::<RpcError>() {
+        Ok(err) => {
+            let message = err.message.to_lowercase();
+            let status = if message.contains("authentication") || message.contains("expired") {
+                403
+            } else {
+                400
+            };
+            let body = json!({ "code": status, "details": err.message, "information": err.message });
+            (body.to_string(), status)
+        }
+        Err(e) => (json!({ "code": 500, "details": e.to_string() }).to_string(), 500),
+    }
+}
@@ -0,0 +1,133 @@
+use crate::models::{Payload, SurrDB};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Schema objects fetched from INFO FOR DB / INFO FOR TABLE for completion
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCache {
+    pub loaded: bool,
+    pub tables: Vec<String>,
+    pub fields: HashMap<String, Vec<String>>,
+    pub indexes: HashMap<String, Vec<String>>,
+    pub functions: Vec<String>,
+    pub params: Vec<String>,
+    pub analyzers: Vec<String>,
+    pub records: HashMap<String, Vec<String>>,
+}
+
+impl SchemaCache {
+    /// All fields of all tables, without duplicates
+    pub fn all_fields(&self) -> Vec<String> {
+        let mut fields: Vec<String> = self.fields.values().flatten().cloned().collect();
+        fields.sort();
+        fields.dedup();
+        fields
+    }
+}
+
+/// Statements that change the schema and make the cache stale
+pub fn changes_schema(query: &str) -> bool {
+    crate::surql::tokenize(query)
+        .iter()
+        .any(|t| t.is_word("DEFINE") || t.is_word("REMOVE") || t.is_word("ALTER"))
+}
+
+/// Keys of an INFO object, accepting both SurrealDB 1.x and 2.x names
+fn info_keys(info: &Value, short: &str, long: &str) -> Vec<String> {
+    let object = info.get(long).or_else(|| info.get(short));
+    let mut keys: Vec<String> = object
+        .and_then(Value::as_object)
+        .map(|map| map.keys().cloned().collect())
+        .unwrap_or_default();
+    keys.sort();
+    keys
+}
+
+/// Parse a /sql response body into statement payloads
+fn payloads(body: &str) -> Result<Vec<Payload>> {
+    Ok(serde_json::from_str(body)?)
+}
+
+/// Quote a table name for interpolation in generated statements
+pub fn quote_ident(name: &str) -> String {
+    format!("⟨{}⟩", name.replace('⟩', "\\⟩"))
+}
+
+impl SurrDB {
+    /// Reload tables, fields, indexes, functions, params and analyzers
+    pub fn refresh_schema(&self) -> Result<()> {
+        let (body, status) = self.requester("INFO FOR DB;")?;
+        if status != 200 {
+            return Err(anyhow::anyhow!("INFO FOR DB failed with status {}", status));
+        }
+
+        let db_info = payloads(&body)?
+            .into_iter()
+            .next()
+            .map(|p| p.result)
+            .unwrap_or_default();
+
+        let mut cache = SchemaCache {
+            loaded: true,
+            tables: info_keys(&db_info, "tb", "tables"),
+            functions: info_keys(&db_info, "fc", "functions"),
+            params: info_keys(&db_info, "pa", "params"),
+            analyzers: info_keys(&db_info, "az", "analyzers"),
+            ..Default::default()
+        };
+
+        if !cache.tables.is_empty() {
+            let query: String = cache
+                .tables
+                .iter()
+                .map(|t| format!("INFO FOR TABLE {};", quote_ident(t)))
+                .collect();
+            let (body, status) = self.requester(&query)?;
+            if status == 200 {
+                for (table, payload) in cache.tables.iter().zip(payloads(&body)?) {
+                    cache.fields.insert(table.clone(), info_keys(&payload.result, "fd", "fields"));
+                    cache.indexes.insert(table.clone(), info_keys(&payload.result, "ix", "indexes"));
+                }
+            }
+        }
+
+        *self.schema_cache.borrow_mut() = cache;
+        Ok(())
+    }
+
+    /// Mark the cache stale so it is reloaded on the next completion
+    pub fn invalidate_schema(&self) {
+        self.schema_cache.borrow_mut().loaded = false;
+    }
+
+    /// Fetch a sample of record ids for a table, cached per table
+    pub fn record_ids(&self, table: &str) -> Vec<String> {
+        if let Some(ids) = self.schema_cache.borrow().records.get(table) {
+            return ids.clone();
+        }
+
+        let query = format!(
+            "SELECT VALUE id FROM {} LIMIT {};",
+            quote_ident(table),
+            self.comple.max(1) * 10
+        );
+        let ids: Vec<String> = match self.requester(&query) {
+            Ok((body, 200)) => payloads(&body)
+                .ok()
+                .and_then(|p| p.into_iter().next())
+                .and_then(|p| p.result.as_array().cloned())
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|id| id.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        self.schema_cache
+            .borrow_mut()
+            .records
+            .insert(table.to_string(), ids.clone());
+        ids
+    }
+}
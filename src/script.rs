@@ -0,0 +1,316 @@
+use crate::models::SurrDB;
+use crate::surql::TokenKind;
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::{Map, Value};
+use std::io::BufRead;
+use std::path::Path;
+use std::time::Instant;
+
+/// Characters of a statement shown in progress lines
+const PREVIEW_WIDTH: usize = 60;
+
+/// First line of a statement, shortened for progress output
+fn preview(statement: &str) -> String {
+    let line = statement
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !crate::surql::only_comments(l))
+        .unwrap_or_default();
+    if line.chars().count() > PREVIEW_WIDTH {
+        format!("{}…", line.chars().take(PREVIEW_WIDTH - 1).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Words of a statement, without whitespace and comments
+fn words(statement: &str) -> Vec<crate::surql::Token<'_>> {
+    crate::surql::tokenize(statement)
+        .into_iter()
+        .filter(|t| !matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+        .collect()
+}
+
+/// Parameter a `LET $name = ...` statement sets
+fn let_name(statement: &str) -> Option<String> {
+    match words(statement).as_slice() {
+        [first, param, ..] if first.is_word("LET") && param.kind == TokenKind::Param => {
+            Some(param.text.trim_start_matches('$').to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Session state later HTTP requests of a script need, since every `/sql`
+/// request starts with a fresh session: USE statements are sent again, and the
+/// values LET statements evaluated to are sent as typed parameters, so a LET
+/// never runs twice
+struct Carried {
+    uses: Vec<String>,
+    vars: Map<String, Value>,
+    http: bool,
+}
+
+impl Carried {
+    fn new(surr: &SurrDB) -> Self {
+        Self {
+            uses: Vec::new(),
+            vars: Map::new(),
+            http: surr.transport != "ws",
+        }
+    }
+
+    /// Parameters set by the statements that later requests will need
+    fn capture(&self, statements: &[String], more: bool) -> Vec<String> {
+        if !self.http || !more {
+            return Vec::new();
+        }
+        let mut names = Vec::new();
+        for name in statements.iter().filter_map(|s| let_name(s)) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Remember the USE statements that ran and the values captured after them
+    fn keep(&mut self, statements: &[String], captured: Map<String, Value>) {
+        if !self.http {
+            return;
+        }
+        for statement in statements {
+            if words(statement).first().is_some_and(|t| t.is_word("USE")) {
+                self.uses.push(statement.clone());
+            }
+        }
+        self.vars.extend(captured);
+    }
+}
+
+/// Reads statements one at a time from a stream without buffering all of it
+pub struct StatementReader<R: BufRead> {
+    reader: R,
+    splitter: crate::surql::Splitter,
+    ready: std::collections::VecDeque<String>,
+    done: bool,
+}
+
+impl<R: BufRead> StatementReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            splitter: crate::surql::Splitter::default(),
+            ready: std::collections::VecDeque::new(),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for StatementReader<R> {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.ready.is_empty() && !self.done {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => {
+                    self.done = true;
+                    let rest = self.splitter.rest();
+                    if !rest.is_empty() {
+                        self.ready.push_back(rest);
+                    }
+                }
+                Ok(_) => self.ready.extend(self.splitter.push(&line)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        self.ready.pop_front().map(Ok)
+    }
+}
+
+impl SurrDB {
+    /// Run statements in order with progress and per-statement timing.
+    /// Stops at the first failure unless `continue_on_error` is set.
+    pub fn run_statements<I>(&self, statements: I, total: Option<usize>, continue_on_error: bool) -> Result<()>
+    where
+        I: IntoIterator<Item = std::io::Result<String>>,
+    {
+        let started = Instant::now();
+        let mut first_error = None;
+        let mut failed = 0;
+        let mut count = 0;
+        let mut schema_changed = false;
+        let mut carried = Carried::new(self);
+        let mut statements = statements.into_iter().enumerate().peekable();
+
+        while let Some((i, statement)) = statements.next() {
+            let statement = statement?;
+            let more = statements.peek().is_some();
+            count += 1;
+            schema_changed |= crate::schema::changes_schema(&statement);
+
+            // Progress goes to stderr so formatted results can be piped
+            let label = match total {
+                Some(total) => format!("[{}/{}]", i + 1, total),
+                None => format!("[{}]", i + 1),
+            };
+            eprintln!("{} {}", label.cyan(), preview(&statement));
+
+            let step = Instant::now();
+            let sent = [statement];
+            let capture = carried.capture(&sent, more);
+            let result = self.contact_surr_after(&carried.uses, &sent[0], &carried.vars, &capture);
+            let elapsed = step.elapsed();
+
+            match result {
+                Ok(captured) => {
+                    carried.keep(&sent, captured);
+                    eprintln!("{}", format!("      done in {:.2?}", elapsed).bright_black());
+                }
+                Err(e) => {
+                    failed += 1;
+                    eprintln!("{}", format!("      failed after {:.2?}", elapsed).red());
+                    first_error.get_or_insert(e);
+                    if !continue_on_error {
+                        eprintln!(
+                            "{}- Stopped at statement {}. Use --continue-on-error to run the rest.",
+                            "[!]".red().bold(),
+                            i + 1
+                        );
+                        break;
+                    }
+                }
+            }
+        }
+
+        if schema_changed {
+            self.invalidate_schema();
+        }
+
+        let summary = format!(
+            "{} statement(s), {} failed in {:.2?}",
+            count,
+            failed,
+            started.elapsed()
+        );
+        match first_error {
+            Some(e) => {
+                eprintln!("{}- {}", "[!]".red().bold(), summary);
+                Err(e)
+            }
+            None => {
+                eprintln!("{}- {}", "[OK]".green().bold(), summary);
+                Ok(())
+            }
+        }
+    }
+
+    /// Read a .surql file and run its statements in order
+    pub fn run_script(&self, path: &Path, continue_on_error: bool) -> Result<()> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        let statements = crate::surql::split_statements(&text);
+        let total = statements.len();
+        self.run_statements(statements.into_iter().map(Ok), Some(total), continue_on_error)
+    }
+
+    /// Run piped input either statement by statement (`each`) or as requests
+    /// of `batch_size` statements (`whole`, 0 sends everything at once)
+    pub fn run_stream<R: BufRead>(
+        &self,
+        reader: R,
+        mode: &str,
+        batch_size: usize,
+        continue_on_error: bool,
+    ) -> Result<()> {
+        let statements = StatementReader::new(reader);
+        if mode == "each" {
+            return self.run_statements(statements, None, continue_on_error);
+        }
+
+        let mut batch: Vec<String> = Vec::new();
+        let mut batches = 0;
+        let mut first_error = None;
+        let mut carried = Carried::new(self);
+        let mut statements = statements.peekable();
+
+        while let Some(statement) = statements.next() {
+            batch.push(statement?);
+            let last = statements.peek().is_none();
+            if !last && (batch_size == 0 || batch.len() < batch_size) {
+                continue;
+            }
+
+            batches += 1;
+            if batch_size > 0 {
+                eprintln!("{} {} statement(s)", format!("[batch {}]", batches).cyan(), batch.len());
+            }
+            let query = batch.join("\n");
+            let schema_changed = crate::schema::changes_schema(&query);
+
+            let capture = carried.capture(&batch, !last);
+            match self.contact_surr_after(&carried.uses, &query, &carried.vars, &capture) {
+                Ok(captured) => carried.keep(&batch, captured),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                    if !continue_on_error {
+                        break;
+                    }
+                }
+            }
+            batch.clear();
+            if schema_changed {
+                self.invalidate_schema();
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn carried(http: bool) -> Carried {
+        Carried { uses: Vec::new(), vars: Map::new(), http }
+    }
+
+    #[test]
+    fn let_statements_name_their_parameter() {
+        assert_eq!(let_name("LET $lim = 5;").as_deref(), Some("lim"));
+        assert_eq!(let_name("-- setup\n  let $p = (CREATE person);").as_deref(), Some("p"));
+        assert_eq!(let_name("SELECT * FROM person WHERE age > $lim;"), None);
+        assert_eq!(let_name("USE NS app DB main;"), None);
+    }
+
+    #[test]
+    fn only_http_requests_followed_by_more_capture() {
+        let batch = ["LET $a = 1;".to_string(), "SELECT $a;".to_string(), "LET $a = 2;".to_string()];
+        assert_eq!(carried(true).capture(&batch, true), ["a"]);
+        assert!(carried(true).capture(&batch, false).is_empty());
+        assert!(carried(false).capture(&batch, true).is_empty());
+    }
+
+    #[test]
+    fn use_statements_and_captured_values_are_kept() {
+        let mut state = carried(true);
+        let mut captured = Map::new();
+        captured.insert("a".to_string(), json!(1));
+        state.keep(&["USE NS app;".to_string(), "LET $a = 1;".to_string()], captured);
+
+        let mut captured = Map::new();
+        captured.insert("a".to_string(), json!([2]));
+        state.keep(&["LET $a = [2];".to_string()], captured);
+
+        assert_eq!(state.uses, ["USE NS app;"]);
+        assert_eq!(state.vars["a"], json!([2]));
+    }
+}
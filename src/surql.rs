@@ -0,0 +1,402 @@
+/// SurrealQL keywords offered for completion and highlighted in the editor
+pub const KEYWORDS: &[&str] = &[
+    "ABORT", "AFTER", "ALL", "ALTER", "ANALYZE", "ANALYZER", "AND", "AS", "ASC", "ASSERT", "BEFORE",
+    "BEGIN", "BREAK", "BY", "CANCEL", "CHANGEFEED", "COLLATE", "COMMIT", "CONTENT", "CONTINUE",
+    "CREATE", "DATABASE", "DB", "DEFAULT", "DEFINE", "DELETE", "DESC", "DIFF", "DROP", "ELSE",
+    "END", "EVENT", "EXPLAIN", "FALSE", "FETCH", "FIELD", "FLEXIBLE", "FOR", "FROM", "FULL",
+    "FUNCTION", "GROUP", "IF", "IGNORE", "IN", "INDEX", "INFO", "INSERT", "INTO", "KILL", "LET",
+    "LIMIT", "LIVE", "MERGE", "NAMESPACE", "NONE", "NOT", "NS", "NULL", "ON", "ONLY", "OR",
+    "ORDER", "OUTPUT", "PARALLEL", "PARAM", "PATCH", "PERMISSIONS", "RELATE", "RELATION", "REMOVE",
+    "REPLACE", "RETURN", "ROOT", "SCHEMAFULL", "SCHEMALESS", "SCOPE", "SEARCH", "SELECT", "SET",
+    "SHOW", "SLEEP", "SPLIT", "START", "TABLE", "THEN", "THROW", "TIMEOUT", "TO", "TOKEN",
+    "TRANSACTION", "TRUE", "TYPE", "UNIQUE", "UNSET", "UPDATE", "UPSERT", "USE", "USER", "VALUE",
+    "VALUES", "WHEN", "WHERE", "WITH",
+];
+
+/// Check whether a word is a SurrealQL keyword
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+/// Kind of a SurrealQL token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Whitespace,
+    Comment,
+    String,
+    Ident,
+    Number,
+    Param,
+    Punct,
+}
+
+/// Token produced by the SurrealQL scanner
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub start: usize,
+    /// False for strings, quoted identifiers and block comments left open
+    pub terminated: bool,
+}
+
+impl Token<'_> {
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// Case-insensitive keyword comparison
+    pub fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Ident && self.text.eq_ignore_ascii_case(word)
+    }
+}
+
+/// Split SurrealQL text into tokens, keeping whitespace and comments
+pub fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, n)| n);
+        let mut terminated = true;
+
+        let kind = if c.is_whitespace() {
+            while chars.next_if(|&(_, n)| n.is_whitespace()).is_some() {}
+            TokenKind::Whitespace
+        } else if c == '#' || (c == '-' && next == Some('-')) || (c == '/' && next == Some('/')) {
+            while chars.next_if(|&(_, n)| n != '\n').is_some() {}
+            TokenKind::Comment
+        } else if c == '/' && next == Some('*') {
+            chars.next();
+            terminated = false;
+            let mut prev = ' ';
+            for (_, n) in chars.by_ref() {
+                if prev == '*' && n == '/' {
+                    terminated = true;
+                    break;
+                }
+                prev = n;
+            }
+            TokenKind::Comment
+        } else if c == '\'' || c == '"' {
+            terminated = scan_quoted(&mut chars, c);
+            TokenKind::String
+        } else if c == '`' || c == '⟨' {
+            terminated = scan_quoted(&mut chars, if c == '`' { '`' } else { '⟩' });
+            TokenKind::Ident
+        } else if c == '$' {
+            while chars.next_if(|&(_, n)| is_word_char(n)).is_some() {}
+            TokenKind::Param
+        } else if c.is_ascii_digit() {
+            while chars
+                .next_if(|&(_, n)| n.is_ascii_alphanumeric() || n == '.' || n == '_')
+                .is_some()
+            {}
+            TokenKind::Number
+        } else if is_word_char(c) {
+            while chars.next_if(|&(_, n)| is_word_char(n)).is_some() {}
+            TokenKind::Ident
+        } else {
+            TokenKind::Punct
+        };
+
+        let end = chars.peek().map_or(input.len(), |&(i, _)| i);
+        tokens.push(Token {
+            kind,
+            text: &input[start..end],
+            start,
+            terminated,
+        });
+    }
+
+    tokens
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Consume a quoted string honoring backslash escapes, returning whether it was closed
+fn scan_quoted(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>, close: char) -> bool {
+    let mut escaped = false;
+    for (_, n) in chars.by_ref() {
+        if escaped {
+            escaped = false;
+        } else if n == '\\' {
+            escaped = true;
+        } else if n == close {
+            return true;
+        }
+    }
+    false
+}
+
+/// Scanner state after reading some SurrealQL text
+#[derive(Debug, Default)]
+pub struct ScanState {
+    /// Complete statements, transaction blocks are kept together
+    pub statements: Vec<String>,
+    /// Text after the last complete statement
+    pub rest: String,
+    /// Open brackets, braces and parentheses in `rest`
+    pub depth: i32,
+    /// A string, quoted identifier or block comment is left open
+    pub open_quote: bool,
+    /// A BEGIN was read without its COMMIT or CANCEL
+    pub in_transaction: bool,
+}
+
+/// Statement splitter fed one piece of text at a time, such as the lines of a
+/// stream. Tokens are scanned once; only a string or block comment still open
+/// at the end of a piece is scanned again with the next one. Pieces should end
+/// at line breaks so no word or number is cut in two.
+#[derive(Debug, Default)]
+pub struct Splitter {
+    /// Text after the last complete statement
+    text: String,
+    /// Bytes of `text` already scanned
+    scanned: usize,
+    depth: i32,
+    /// A token other than whitespace or comments was read in this statement
+    started: bool,
+    in_transaction: bool,
+    open_quote: bool,
+}
+
+impl Splitter {
+    /// Add a piece of text and return the statements it completes
+    pub fn push(&mut self, piece: &str) -> Vec<String> {
+        self.text.push_str(piece);
+        let base = self.scanned;
+        let mut ends = Vec::new();
+        self.open_quote = false;
+
+        for token in tokenize(&self.text[base..]) {
+            if !token.terminated {
+                // Scanned again from its start once more text arrives
+                self.open_quote = true;
+                break;
+            }
+            self.scanned = base + token.end();
+            match token.kind {
+                TokenKind::Whitespace | TokenKind::Comment => {}
+                TokenKind::Punct => {
+                    self.started = true;
+                    match token.text {
+                        "(" | "[" | "{" => self.depth += 1,
+                        ")" | "]" | "}" => self.depth -= 1,
+                        ";" if self.depth <= 0 => {
+                            self.depth = 0;
+                            self.started = false;
+                            if !self.in_transaction {
+                                ends.push(self.scanned);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {
+                    if !self.started && self.depth == 0 {
+                        if token.is_word("BEGIN") {
+                            self.in_transaction = true;
+                        } else if token.is_word("COMMIT") || token.is_word("CANCEL") {
+                            self.in_transaction = false;
+                        }
+                    }
+                    self.started = true;
+                }
+            }
+        }
+
+        let mut statements = Vec::new();
+        let mut start = 0;
+        for end in ends {
+            let statement = self.text[start..end].trim();
+            if statement != ";" {
+                statements.push(statement.to_string());
+            }
+            start = end;
+        }
+        self.text.drain(..start);
+        self.scanned -= start;
+        statements
+    }
+
+    /// Text after the last complete statement, empty when only comments are left
+    pub fn rest(&self) -> String {
+        if only_comments(&self.text) {
+            String::new()
+        } else {
+            self.text.trim().to_string()
+        }
+    }
+}
+
+/// Split SurrealQL text on top-level semicolons
+pub fn scan(input: &str) -> ScanState {
+    let mut splitter = Splitter::default();
+    let statements = splitter.push(input);
+    ScanState {
+        statements,
+        rest: splitter.rest(),
+        depth: splitter.depth,
+        open_quote: splitter.open_quote,
+        in_transaction: splitter.in_transaction,
+    }
+}
+
+/// Split SurrealQL text into statements, the unterminated tail becomes the last one
+pub fn split_statements(input: &str) -> Vec<String> {
+    let mut state = scan(input);
+    if !state.rest.is_empty() {
+        state.statements.push(state.rest);
+    }
+    state.statements
+}
+
+/// Check whether text holds nothing but whitespace and comments
+pub fn only_comments(input: &str) -> bool {
+    tokenize(input)
+        .iter()
+        .all(|t| matches!(t.kind, TokenKind::Whitespace | TokenKind::Comment))
+}
+
+/// Decide whether REPL input is ready to be sent.
+///
+/// Dot-commands are always single line. Statements need a terminating
+/// semicolon outside strings, comments, brackets and transaction blocks;
+/// an empty line submits whatever is buffered once brackets are balanced.
+pub fn is_complete(input: &str) -> bool {
+    let trimmed = input.trim();
+    if trimmed.is_empty() || trimmed.starts_with('.') {
+        return true;
+    }
+
+    let state = scan(input);
+    if state.open_quote {
+        return false;
+    }
+
+    let blank_line = input.contains('\n') && input.rsplit('\n').next().is_some_and(|l| l.trim().is_empty());
+    if blank_line && state.depth <= 0 && !state.in_transaction {
+        return true;
+    }
+
+    state.rest.is_empty() && state.depth <= 0 && !state.in_transaction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_top_level_semicolons() {
+        assert_eq!(
+            split_statements("SELECT * FROM a; CREATE b;\nRETURN 1"),
+            vec!["SELECT * FROM a;", "CREATE b;", "RETURN 1"]
+        );
+    }
+
+    #[test]
+    fn ignores_semicolons_in_strings_comments_and_brackets() {
+        let input = "CREATE a SET s = 'x;y', t = \"it\\\"s;\";\n\
+                     -- note; here\n\
+                     /* block; */ SELECT * FROM ⟨odd;name⟩, `b;c` WHERE x = { a: 1; };";
+        let statements = split_statements(input);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].ends_with("t = \"it\\\"s;\";"));
+        assert!(statements[1].ends_with("WHERE x = { a: 1; };"));
+    }
+
+    #[test]
+    fn keeps_transactions_together() {
+        let statements = split_statements("BEGIN;\nCREATE a;\nCREATE b;\nCOMMIT;\nRETURN 1;");
+        assert_eq!(statements, vec!["BEGIN;\nCREATE a;\nCREATE b;\nCOMMIT;", "RETURN 1;"]);
+
+        let statements = split_statements("begin transaction; CREATE a; cancel transaction;");
+        assert_eq!(statements.len(), 1);
+    }
+
+    #[test]
+    fn begin_is_only_a_keyword_at_statement_start() {
+        let statements = split_statements("CREATE event SET name = begin; RETURN 1;");
+        assert_eq!(statements, vec!["CREATE event SET name = begin;", "RETURN 1;"]);
+    }
+
+    #[test]
+    fn drops_empty_statements_and_trailing_comments() {
+        assert_eq!(split_statements(";; RETURN 1;;\n-- done"), vec!["RETURN 1;"]);
+        assert!(split_statements("-- only a comment\n/* and a block */").is_empty());
+    }
+
+    #[test]
+    fn reports_open_quotes_and_depth() {
+        let state = scan("CREATE a SET s = 'open");
+        assert!(state.open_quote);
+        assert!(state.statements.is_empty());
+
+        let state = scan("DEFINE FUNCTION fn::a() { RETURN 1;");
+        assert_eq!(state.depth, 1);
+        assert!(state.statements.is_empty());
+
+        assert!(scan("/* open comment").open_quote);
+    }
+
+    #[test]
+    fn completes_statements() {
+        assert!(is_complete("SELECT * FROM a;"));
+        assert!(is_complete(".help"));
+        assert!(is_complete("   "));
+        assert!(!is_complete("SELECT * FROM a"));
+        assert!(!is_complete("SELECT ';"));
+        assert!(!is_complete("BEGIN;\nCREATE a;"));
+        assert!(is_complete("BEGIN;\nCREATE a;\nCOMMIT;"));
+        assert!(!is_complete("DEFINE FUNCTION fn::a() {\n"));
+        // An empty line sends the buffer once brackets are balanced
+        assert!(is_complete("SELECT * FROM a\n"));
+        assert!(!is_complete("SELECT * FROM a WHERE x = {\n"));
+    }
+
+    #[test]
+    fn splitter_matches_scan_when_fed_line_by_line() {
+        let input = "CREATE a SET s = 'multi\nline; string';\n\
+                     /* block ;\ncomment */ SELECT * FROM b;\n\
+                     BEGIN;\nCREATE c;\nCOMMIT;\n\
+                     RETURN 5";
+        let mut splitter = Splitter::default();
+        let mut statements = Vec::new();
+        for line in input.split_inclusive('\n') {
+            statements.extend(splitter.push(line));
+        }
+        statements.push(splitter.rest());
+        assert_eq!(statements, split_statements(input));
+    }
+
+    #[test]
+    fn splitter_only_keeps_the_unfinished_statement() {
+        let mut splitter = Splitter::default();
+        assert_eq!(splitter.push("RETURN 1; RETURN 'a\n"), vec!["RETURN 1;"]);
+        assert_eq!(splitter.push("b';\n"), vec!["RETURN 'a\nb';"]);
+        assert_eq!(splitter.rest(), "");
+    }
+
+    #[test]
+    fn tokenizes_params_numbers_and_words() {
+        let kinds: Vec<(TokenKind, &str)> = tokenize("$name >= 1.5e3 AND x")
+            .iter()
+            .filter(|t| t.kind != TokenKind::Whitespace)
+            .map(|t| (t.kind, t.text))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (TokenKind::Param, "$name"),
+                (TokenKind::Punct, ">"),
+                (TokenKind::Punct, "="),
+                (TokenKind::Number, "1.5e3"),
+                (TokenKind::Ident, "AND"),
+                (TokenKind::Ident, "x"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::models::SurrDB;
+use anyhow::Result;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Set once the --insecure warning has been shown
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Warn, once per run, that certificates are not checked
+fn warn_insecure() {
+    if !WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "{}",
+            colored::Colorize::bold(colored::Colorize::red(
+                "[!]- TLS certificate verification is DISABLED (insecure). \
+                 Anyone on the network can read and change this traffic."
+            ))
+        );
+    }
+}
+
+fn read_file(kind: &str, path: &str) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| anyhow::anyhow!("Cannot read {} {}: {}", kind, path, e))
+}
+
+/// Split a PEM bundle into its certificates
+fn pem_certificates(path: &str) -> Result<Vec<Vec<u8>>> {
+    let text = String::from_utf8(read_file("CA bundle", path)?)
+        .map_err(|_| anyhow::anyhow!("CA bundle {} is not PEM text", path))?;
+    const END: &str = "-----END CERTIFICATE-----";
+
+    let certs: Vec<Vec<u8>> = text
+        .split_inclusive(END)
+        .filter_map(|block| block.find("-----BEGIN CERTIFICATE-----").map(|start| &block[start..]))
+        .filter(|block| block.ends_with(END))
+        .map(|block| block.as_bytes().to_vec())
+        .collect();
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("No certificates found in {}", path));
+    }
+    Ok(certs)
+}
+
+impl SurrDB {
+    /// `host:port` used in URLs. The TLS server name, when set, replaces the host name
+    /// and an SSH tunnel replaces the port with its local end.
+    pub fn authority(&self) -> Result<String> {
+        let tunnel = self.tunnel_port()?;
+        let (name, port) = match self.host.rsplit_once(':') {
+            Some((name, port)) => (name, Some(port.to_string())),
+            None => (self.host.as_str(), None),
+        };
+        let name = match self.pinned_name(tunnel.is_some()) {
+            Some(pinned) => pinned,
+            // An address of the host means nothing on this side of the tunnel
+            None if tunnel.is_some() => "127.0.0.1",
+            None => name,
+        };
+        Ok(match tunnel.map(|port| port.to_string()).or(port) {
+            Some(port) => format!("{}:{}", name, port),
+            None => name.to_string(),
+        })
+    }
+
+    /// Name in the URL that is resolved to [`Self::socket_addr`] instead of DNS:
+    /// the server name, or the host name when an SSH tunnel carries the connection
+    fn pinned_name(&self, tunnel: bool) -> Option<&str> {
+        if !self.tls.server_name.is_empty() {
+            return Some(&self.tls.server_name);
+        }
+        let name = self.host.rsplit_once(':').map_or(self.host.as_str(), |(name, _)| name);
+        (tunnel && !name.starts_with('[') && name.parse::<IpAddr>().is_err()).then_some(name)
+    }
+
+    /// Address to connect to, which differs from the URL when a server name or SSH tunnel is set
+    pub fn socket_addr(&self) -> Result<SocketAddr> {
+        if let Some(port) = self.tunnel_port()? {
+            return Ok(SocketAddr::from(([127, 0, 0, 1], port)));
+        }
+        self.host
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Cannot resolve host {}", self.host))
+    }
+
+    /// New HTTP client with the timeouts and TLS settings of the connection
+    pub fn build_http_client(&self) -> Result<reqwest::blocking::Client> {
+        let tls = &self.tls;
+        let mut builder = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(self.timeout))
+            .connect_timeout(Duration::from_secs(self.connect_timeout))
+            .tcp_keepalive(Duration::from_secs(60));
+
+        if !tls.ca_cert.is_empty() {
+            for pem in pem_certificates(&tls.ca_cert)? {
+                let cert = reqwest::Certificate::from_pem(&pem)
+                    .map_err(|e| anyhow::anyhow!("Invalid certificate in {}: {}", tls.ca_cert, e))?;
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if !tls.client_cert.is_empty() || !tls.client_key.is_empty() {
+            let (cert, key) = self.client_identity()?;
+            let identity = reqwest::Identity::from_pkcs8_pem(&cert, &key)
+                .map_err(|e| anyhow::anyhow!("Invalid client certificate or key: {}", e))?;
+            builder = builder.identity(identity);
+        }
+        // Nothing in between is needed once an SSH tunnel reaches the host
+        let tunnel = self.tunnel_port()?.is_some();
+        builder = if tunnel { builder.no_proxy() } else { self.apply_proxy(builder)? };
+        if let Some(name) = self.pinned_name(tunnel) {
+            // The URL names the server, the connection still goes to the host or tunnel
+            builder = builder.resolve(name, self.socket_addr()?);
+        }
+        if tls.insecure {
+            warn_insecure();
+            builder = builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// TLS connector for the `wss://` transport
+    pub fn tls_connector(&self) -> Result<native_tls::TlsConnector> {
+        let tls = &self.tls;
+        let mut builder = native_tls::TlsConnector::builder();
+
+        if !tls.ca_cert.is_empty() {
+            for pem in pem_certificates(&tls.ca_cert)? {
+                let cert = native_tls::Certificate::from_pem(&pem)
+                    .map_err(|e| anyhow::anyhow!("Invalid certificate in {}: {}", tls.ca_cert, e))?;
+                builder.add_root_certificate(cert);
+            }
+        }
+        if !tls.client_cert.is_empty() || !tls.client_key.is_empty() {
+            let (cert, key) = self.client_identity()?;
+            let identity = native_tls::Identity::from_pkcs8(&cert, &key)
+                .map_err(|e| anyhow::anyhow!("Invalid client certificate or key: {}", e))?;
+            builder.identity(identity);
+        }
+        if tls.insecure {
+            warn_insecure();
+            builder
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true);
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Client certificate and key, which must be given together
+    fn client_identity(&self) -> Result<(Vec<u8>, Vec<u8>)> {
+        let tls = &self.tls;
+        if tls.client_cert.is_empty() || tls.client_key.is_empty() {
+            return Err(anyhow::anyhow!("A client certificate needs both client_cert and client_key"));
+        }
+        Ok((
+            read_file("client certificate", &tls.client_cert)?,
+            read_file("client key", &tls.client_key)?,
+        ))
+    }
+}
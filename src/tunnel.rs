@@ -0,0 +1,174 @@
+use crate::models::SurrDB;
+use anyhow::Result;
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Environment variable replacing the `ssh` command, e.g. `ssh -i ~/.ssh/db`
+pub const SSH_COMMAND_ENV: &str = "SURRCLI_SSH_COMMAND";
+
+/// Longest wait for ssh to open the forward, which includes typing a password
+const OPEN_WAIT: Duration = Duration::from_secs(60);
+
+/// SSH local forward from a free port on 127.0.0.1 to the database host.
+/// The ssh process is stopped when the tunnel is dropped.
+#[derive(Debug)]
+pub struct Tunnel {
+    child: Child,
+    bastion: String,
+    target: String,
+    pub port: u16,
+}
+
+/// Split `[user@]bastion[:port]` into the ssh destination and port.
+/// IPv6 bastions are written bare, or in brackets when a port follows.
+pub fn parse_bastion(text: &str) -> Result<(String, Option<u16>)> {
+    let invalid = || anyhow::anyhow!("Invalid SSH bastion {}. Use [user@]host[:port].", text);
+    if text.is_empty() || text.starts_with('-') || text.contains(char::is_whitespace) {
+        return Err(invalid());
+    }
+    let (user, address) = match text.rsplit_once('@') {
+        Some(("", _)) => return Err(invalid()),
+        Some((user, address)) => (Some(user), address),
+        None => (None, text),
+    };
+
+    let (host, port) = match address.strip_prefix('[') {
+        Some(rest) => match rest.split_once(']').ok_or_else(invalid)? {
+            (host, "") => (host, None),
+            (host, after) => (host, Some(after.strip_prefix(':').ok_or_else(invalid)?)),
+        },
+        None => match address.rsplit_once(':') {
+            // A bare IPv6 address has more than one colon and no port
+            Some((host, port)) if !host.contains(':') => (host, Some(port)),
+            _ => (address, None),
+        },
+    };
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    let port = port
+        .map(|port| port.parse().map_err(|_| anyhow::anyhow!("Invalid SSH port in {}", text)))
+        .transpose()?;
+
+    let destination = match user {
+        Some(user) => format!("{}@{}", user, host),
+        None => host.to_string(),
+    };
+    Ok((destination, port))
+}
+
+/// ssh program and arguments from SURRCLI_SSH_COMMAND, or plain `ssh`
+fn ssh_command() -> Vec<String> {
+    std::env::var(SSH_COMMAND_ENV)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| value.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_else(|| vec!["ssh".to_string()])
+}
+
+impl Tunnel {
+    /// Start `ssh -N -L` and wait until the local end accepts connections
+    fn open(bastion: &str, target: &str) -> Result<Tunnel> {
+        let (destination, ssh_port) = parse_bastion(bastion)?;
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+
+        let command = ssh_command();
+        let mut ssh = Command::new(&command[0]);
+        ssh.args(&command[1..])
+            .args(["-N", "-o", "ExitOnForwardFailure=yes"])
+            .arg("-L")
+            .arg(format!("127.0.0.1:{}:{}", port, target));
+        if let Some(ssh_port) = ssh_port {
+            ssh.arg("-p").arg(ssh_port.to_string());
+        }
+        // stdin stays attached so ssh can ask for a password or passphrase
+        let child = ssh
+            .arg(&destination)
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Cannot start {}: {}", command[0], e))?;
+
+        let mut tunnel = Tunnel {
+            child,
+            bastion: bastion.to_string(),
+            target: target.to_string(),
+            port,
+        };
+        let started = Instant::now();
+        loop {
+            if let Some(status) = tunnel.child.try_wait()? {
+                return Err(anyhow::anyhow!("SSH tunnel through {} failed: ssh exited with {}", bastion, status));
+            }
+            if TcpStream::connect(("127.0.0.1", port)).is_ok() {
+                return Ok(tunnel);
+            }
+            if started.elapsed() > OPEN_WAIT {
+                return Err(anyhow::anyhow!("Timed out opening the SSH tunnel through {}", bastion));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+impl Drop for Tunnel {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl SurrDB {
+    /// Local port of the SSH tunnel to the host, opening the tunnel when `ssh` is set
+    /// and replacing it when the bastion or host changed or ssh has exited
+    pub fn tunnel_port(&self) -> Result<Option<u16>> {
+        let mut tunnel = self.tunnel.borrow_mut();
+        if self.ssh.is_empty() {
+            tunnel.take();
+            return Ok(None);
+        }
+        if let Some(open) = tunnel.as_mut()
+            && open.bastion == self.ssh
+            && open.target == self.host
+            && open.child.try_wait()?.is_none()
+        {
+            return Ok(Some(open.port));
+        }
+
+        tunnel.take();
+        let open = Tunnel::open(&self.ssh, &self.host)?;
+        let port = open.port;
+        *tunnel = Some(open);
+        Ok(Some(port))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bastion(text: &str) -> (String, Option<u16>) {
+        parse_bastion(text).unwrap()
+    }
+
+    #[test]
+    fn bastions_split_into_destination_and_port() {
+        assert_eq!(bastion("bastion"), ("bastion".to_string(), None));
+        assert_eq!(bastion("ops@bastion:2222"), ("ops@bastion".to_string(), Some(2222)));
+        assert_eq!(bastion("bastion.example:22"), ("bastion.example".to_string(), Some(22)));
+        assert_eq!(bastion("ops@2001:db8::1"), ("ops@2001:db8::1".to_string(), None));
+        assert_eq!(bastion("[2001:db8::1]:2222"), ("2001:db8::1".to_string(), Some(2222)));
+        assert_eq!(bastion("ops@[::1]"), ("ops@::1".to_string(), None));
+    }
+
+    #[test]
+    fn incomplete_bastions_are_rejected() {
+        let texts = [
+            "", "ops@", "@bastion", "bastion:", "bastion:ssh", "bastion:99999",
+            "[::1", "[::1]2222", "-oProxyCommand=x", "ops@bastion host",
+        ];
+        for text in texts {
+            assert!(parse_bastion(text).is_err(), "{}", text);
+        }
+    }
+}
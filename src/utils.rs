@@ -7,6 +7,11 @@ pub fn print_err(s: &str) {
     println!("{}- {}", "[!]".red().bold(), s);
 }
 
+/// Print error message with red [!] prefix on stderr
+pub fn eprint_err(s: &str) {
+    eprintln!("{}- {}", "[!]".red().bold(), s);
+}
+
 /// Print success message with green [OK] prefix
 pub fn print_suc(s: &str) {
     println!("{}- {}", "[OK]".green().bold(), s);
@@ -31,40 +36,233 @@ impl SurrDB {
         match var {
             "user" | "User" => {
                 self.user = value.to_string();
+                self.set_source(&["user", "pass"], "set");
                 print_suc(&format!("Use <- {}", self.user));
-                self.pass = rpassword::prompt_password(&format!("[password:{}]: ", self.user))
+                self.pass = rpassword::prompt_password(format!("[password:{}]: ", self.user))
                     .unwrap_or_else(|_| String::new());
                 println!();
+                self.resignin();
             }
             "host" | "Host" => {
                 self.host = value.to_string();
+                self.set_source(&["host"], "set");
                 print_suc(&format!("Host <- {}", self.host));
+                self.reset_session();
+            }
+            "color" | "Color" => match value {
+                "on" | "true" => {
+                    self.color = true;
+                    colored::control::unset_override();
+                    print_suc("Color <- on");
+                }
+                "off" | "false" => {
+                    self.color = false;
+                    colored::control::set_override(false);
+                    print_suc("Color <- off");
+                }
+                _ => print_err("Invalid value. Must be on or off."),
+            },
+            "format" | "Format" => {
+                if crate::render::FORMATS.contains(&value) {
+                    self.format = value.to_string();
+                    self.set_source(&["format"], "set");
+                    print_suc(&format!("Format <- {}", self.format));
+                } else {
+                    print_err(&format!(
+                        "Invalid format. Must be one of {}.",
+                        crate::render::FORMATS.join(", ")
+                    ));
+                }
             }
+            "meta" | "Meta" => match value {
+                "on" | "true" => {
+                    self.meta = true;
+                    print_suc("Meta <- on");
+                }
+                "off" | "false" => {
+                    self.meta = false;
+                    print_suc("Meta <- off");
+                }
+                _ => print_err("Invalid value. Must be on or off."),
+            },
             "pretty" | "Pretty" => {
                 self.pretty = !self.pretty;
                 print_suc(&format!("Pretty print <- {}", self.pretty));
             }
+            "log" | "Log" => match value {
+                "on" | "true" => {
+                    self.log = true;
+                    print_suc("Log <- on");
+                }
+                "off" | "false" => {
+                    self.log = false;
+                    print_suc("Log <- off");
+                }
+                _ => print_err("Invalid value. Must be on or off."),
+            },
+            "log_output" => match value.parse::<usize>() {
+                Ok(n) => {
+                    self.log_output = n;
+                    print_suc(&format!("Log output limit <- {} bytes", n));
+                }
+                Err(_) => print_err("Invalid size. Must be a number of bytes."),
+            },
+            "log_keep" => match value.parse::<usize>() {
+                Ok(n) => {
+                    self.log_keep = n;
+                    print_suc(&format!("Log keep <- {} entries", n));
+                }
+                Err(_) => print_err("Invalid number of entries."),
+            },
+            "log_days" => match value.parse::<u64>() {
+                Ok(n) => {
+                    self.log_days = n;
+                    print_suc(&format!("Log days <- {}", n));
+                }
+                Err(_) => print_err("Invalid number of days."),
+            },
+            "comple" | "Comple" | "suggestion" => match value.parse::<usize>() {
+                Ok(n) => {
+                    self.comple = n;
+                    print_suc(&format!("Suggestion <- {}", self.comple));
+                }
+                Err(_) => print_err("Invalid number of suggestions."),
+            },
             "ns" | "NS" | "nameserver" | "namespace" => {
                 self.namespace = value.to_string();
+                self.set_source(&["namespace"], "set");
                 print_suc(&format!("Namespace <- {}", self.namespace));
+                self.reset_session();
             }
             "db" | "DB" | "database" => {
                 self.database = value.to_string();
+                self.set_source(&["database"], "set");
                 print_suc(&format!("Database <- {}", self.database));
+                self.reset_session();
             }
             "schema" | "Schema" | "sch" => {
                 if value == "http" || value == "https" {
                     self.schema = value.to_string();
+                    self.set_source(&["schema"], "set");
                     print_suc(&format!("Schema <- {}", self.schema));
+                    self.reset_session();
                 } else {
                     print_err("Invalid schema. Must be http or https.");
                 }
             }
+            "auth" | "Auth" => {
+                if ["root", "ns", "db", "record"].contains(&value) {
+                    self.auth = value.to_string();
+                    self.set_source(&["auth"], "set");
+                    print_suc(&format!("Auth <- {}", self.auth));
+                    self.resignin();
+                } else {
+                    print_err("Invalid auth level. Must be root, ns, db or record.");
+                }
+            }
+            "access" | "Access" => {
+                self.access = value.to_string();
+                self.set_source(&["access"], "set");
+                print_suc(&format!("Access <- {}", self.access));
+                self.resignin();
+            }
+            "ca_cert" | "client_cert" | "client_key" => {
+                let value = if value == "none" { "" } else { value };
+                if !value.is_empty() && !std::path::Path::new(value).is_file() {
+                    print_err(&format!("No such file: {}", value));
+                    return;
+                }
+                let field = match var {
+                    "ca_cert" => &mut self.tls.ca_cert,
+                    "client_cert" => &mut self.tls.client_cert,
+                    _ => &mut self.tls.client_key,
+                };
+                *field = value.to_string();
+                self.set_source(&[var], "set");
+                print_suc(&format!("{} <- {}", var, if value.is_empty() { "none" } else { value }));
+                self.reset_session();
+            }
+            "server_name" => {
+                let value = if value == "none" { "" } else { value };
+                self.tls.server_name = value.to_string();
+                self.set_source(&["server_name"], "set");
+                print_suc(&format!("server_name <- {}", if value.is_empty() { "none" } else { value }));
+                self.reset_session();
+            }
+            "insecure" => match value {
+                "on" | "true" => {
+                    self.tls.insecure = true;
+                    self.set_source(&["insecure"], "set");
+                    print_err("Insecure <- on. TLS certificates are no longer verified.");
+                    self.reset_session();
+                }
+                "off" | "false" => {
+                    self.tls.insecure = false;
+                    self.set_source(&["insecure"], "set");
+                    print_suc("Insecure <- off");
+                    self.reset_session();
+                }
+                _ => print_err("Invalid value. Must be on or off."),
+            },
+            "proxy" => {
+                let value = if value == "none" { "" } else { value };
+                if !value.is_empty()
+                    && let Err(e) = crate::proxy::validate(value)
+                {
+                    print_err(&e.to_string());
+                    return;
+                }
+                self.proxy = value.to_string();
+                self.set_source(&["proxy"], "set");
+                let shown = if value.is_empty() { "none".to_string() } else { crate::dsn::redact(value) };
+                print_suc(&format!("proxy <- {}", shown));
+                self.reset_session();
+            }
+            "ssh" => {
+                let value = if value == "none" { "" } else { value };
+                if !value.is_empty()
+                    && let Err(e) = crate::tunnel::parse_bastion(value)
+                {
+                    print_err(&e.to_string());
+                    return;
+                }
+                self.ssh = value.to_string();
+                // The old tunnel is closed now, a new one opens with the next statement
+                self.tunnel.borrow_mut().take();
+                self.set_source(&["ssh"], "set");
+                print_suc(&format!("ssh <- {}", if value.is_empty() { "none" } else { value }));
+                self.reset_session();
+            }
+            "transport" | "Transport" => {
+                if value == "http" || value == "ws" {
+                    self.transport = value.to_string();
+                    self.set_source(&["transport"], "set");
+                    print_suc(&format!("Transport <- {}", self.transport));
+                    self.reset_session();
+                } else {
+                    print_err("Invalid transport. Must be http or ws.");
+                }
+            }
             _ => {
                 print_err("No options.");
             }
         }
     }
+
+    /// Drop the token and sign in again with the current credentials
+    fn resignin(&self) {
+        self.reset_session();
+        let result = match self.transport.as_str() {
+            "ws" => self.requester("RETURN true;").map(|(_, status)| status),
+            _ => self.signin().map(|(_, status)| status),
+        };
+
+        match result {
+            Ok(200) => print_suc("Signed in."),
+            Ok(_) => print_err("Signin failed. Check credentials with .set user <username>."),
+            Err(e) => print_err(&format!("Signin failed: {}", e)),
+        }
+    }
 }
 
 /// Read password from stdin without echo
@@ -76,13 +274,44 @@ pub fn get_no_echo(prompt: &str) -> Result<String, anyhow::Error> {
     Ok(password)
 }
 
-/// Read input from stdin if piped
-pub fn from_stdin() -> Option<String> {
+/// Locked stdin if input is piped
+pub fn from_stdin() -> Option<io::StdinLock<'static>> {
     if atty::is(atty::Stream::Stdin) {
         return None;
     }
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).ok()?;
-    Some(input)
+    Some(io::stdin().lock())
+}
+
+/// Score how well `pattern` matches `text` as a case-insensitive subsequence.
+/// Consecutive characters and word starts score higher, no match gives None.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    if pattern.is_empty() {
+        return None;
+    }
+
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut last: Option<usize> = None;
+    for (i, c) in text.iter().enumerate() {
+        if next == pattern.len() {
+            break;
+        }
+        if *c != pattern[next] {
+            continue;
+        }
+        score += 1;
+        if last.is_some_and(|l| l + 1 == i) {
+            score += 3;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 2;
+        }
+        last = Some(i);
+        next += 1;
+    }
+
+    (next == pattern.len()).then_some(score)
 }
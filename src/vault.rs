@@ -0,0 +1,257 @@
+use crate::db::{
+    db_put_vault_entry, db_replace_vault, db_vault_entries, db_vault_entry, db_vault_key,
+};
+use crate::models::{SurrDB, VaultEntry, VaultKey};
+use anyhow::Result;
+use argon2::Argon2;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::sync::Mutex;
+
+/// Environment variable holding the passphrase for non-interactive use
+pub const PASSPHRASE_ENV: &str = "SURRCLI_VAULT_PASSPHRASE";
+
+/// Plaintext encrypted with the key to check a passphrase
+const VERIFIER: &[u8] = b"surrcli-vault";
+
+/// Key of the unlocked vault, kept for the rest of the session
+static SESSION_KEY: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+
+/// Derive the encryption key from a passphrase with Argon2id
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow::anyhow!("Cannot derive vault key: {}", e))?;
+    Ok(key)
+}
+
+fn encrypt(key: &[u8; 32], plain: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let secret = cipher
+        .encrypt(&nonce, plain)
+        .map_err(|_| anyhow::anyhow!("Cannot encrypt vault entry"))?;
+    Ok((nonce.to_vec(), secret))
+}
+
+fn decrypt(key: &[u8; 32], nonce: &[u8], secret: &[u8]) -> Result<Vec<u8>> {
+    if nonce.len() != 12 {
+        return Err(anyhow::anyhow!("Corrupt vault entry"));
+    }
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), secret)
+        .map_err(|_| anyhow::anyhow!("Wrong vault passphrase"))
+}
+
+/// New salt and verifier for a passphrase, with the derived key
+fn new_key(passphrase: &str) -> Result<([u8; 32], VaultKey)> {
+    let mut salt = vec![0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive_key(passphrase, &salt)?;
+    let (nonce, verifier) = encrypt(&key, VERIFIER)?;
+    Ok((key, VaultKey { salt, nonce, verifier }))
+}
+
+/// Passphrase from the environment or the terminal
+fn read_passphrase(prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    Ok(rpassword::prompt_password(prompt)?)
+}
+
+/// Ask twice for a new passphrase
+fn prompt_new_passphrase() -> Result<String> {
+    let passphrase = rpassword::prompt_password("[new vault passphrase]: ")?;
+    if passphrase.is_empty() {
+        return Err(anyhow::anyhow!("Vault passphrase cannot be empty"));
+    }
+    if rpassword::prompt_password("[repeat passphrase]: ")? != passphrase {
+        return Err(anyhow::anyhow!("Passphrases do not match"));
+    }
+    Ok(passphrase)
+}
+
+/// Check a passphrase against a verifier and return its key
+fn check_passphrase(passphrase: &str, stored: &VaultKey) -> Result<[u8; 32]> {
+    let key = derive_key(passphrase, &stored.salt)?;
+    if decrypt(&key, &stored.nonce, &stored.verifier)? != VERIFIER {
+        return Err(anyhow::anyhow!("Wrong vault passphrase"));
+    }
+    Ok(key)
+}
+
+/// Check a passphrase against the stored verifier and return its key
+fn open_with(passphrase: &str) -> Result<[u8; 32]> {
+    let stored = db_vault_key()?.ok_or_else(|| anyhow::anyhow!("Vault is not initialized"))?;
+    check_passphrase(passphrase, &stored)
+}
+
+/// Decrypt every entry with the old key and encrypt it again with the new one
+fn reencrypt(old: &[u8; 32], key: &[u8; 32], entries: Vec<VaultEntry>) -> Result<Vec<VaultEntry>> {
+    entries
+        .into_iter()
+        .map(|entry| {
+            let plain = decrypt(old, &entry.nonce, &entry.secret)?;
+            let (nonce, secret) = encrypt(key, &plain)?;
+            Ok(VaultEntry { idx: entry.idx, nonce, secret })
+        })
+        .collect()
+}
+
+/// Check whether the vault key is cached for this session
+pub fn is_unlocked() -> bool {
+    SESSION_KEY.lock().map(|key| key.is_some()).unwrap_or(false)
+}
+
+/// Unlock the vault once per session, creating it on first use
+pub fn unlock() -> Result<[u8; 32]> {
+    let mut cached = SESSION_KEY.lock().map_err(|_| anyhow::anyhow!("Vault lock poisoned"))?;
+    if let Some(key) = *cached {
+        return Ok(key);
+    }
+
+    let key = if db_vault_key()?.is_some() {
+        open_with(&read_passphrase("[vault passphrase]: ")?)?
+    } else {
+        crate::utils::print_suc("Creating the password vault.");
+        let passphrase = match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) => passphrase,
+            Err(_) => prompt_new_passphrase()?,
+        };
+        let (key, stored) = new_key(&passphrase)?;
+        db_replace_vault(&stored, &[])?;
+        key
+    };
+
+    *cached = Some(key);
+    Ok(key)
+}
+
+/// Forget the session key
+pub fn lock() {
+    if let Ok(mut cached) = SESSION_KEY.lock() {
+        if let Some(key) = cached.as_mut() {
+            key.fill(0);
+        }
+        *cached = None;
+    }
+}
+
+/// Encrypt and store a secret under a name
+pub fn store(name: &str, secret: &str) -> Result<()> {
+    let key = unlock()?;
+    let (nonce, cipher) = encrypt(&key, secret.as_bytes())?;
+    db_put_vault_entry(name, &nonce, &cipher)
+}
+
+/// Decrypt a stored secret
+pub fn fetch(name: &str) -> Result<String> {
+    let (nonce, secret) =
+        db_vault_entry(name)?.ok_or_else(|| anyhow::anyhow!("No vault entry named {}", name))?;
+    let key = unlock()?;
+    Ok(String::from_utf8(decrypt(&key, &nonce, &secret)?)?)
+}
+
+/// Re-encrypt every entry under a new passphrase
+pub fn rotate() -> Result<()> {
+    if db_vault_key()?.is_none() {
+        return Err(anyhow::anyhow!("Vault is not initialized"));
+    }
+
+    // The current passphrase is always asked again, even when unlocked
+    let old = open_with(&rpassword::prompt_password("[current vault passphrase]: ")?)?;
+    let entries = db_vault_entries()?;
+    // Every entry must open with the current key before a new passphrase is asked
+    for entry in &entries {
+        decrypt(&old, &entry.nonce, &entry.secret)?;
+    }
+
+    let (key, stored) = new_key(&prompt_new_passphrase()?)?;
+    let entries = reencrypt(&old, &key, entries)?;
+    db_replace_vault(&stored, &entries)?;
+
+    lock();
+    *SESSION_KEY.lock().map_err(|_| anyhow::anyhow!("Vault lock poisoned"))? = Some(key);
+    Ok(())
+}
+
+impl SurrDB {
+    /// Use the password stored for a profile, if it has one
+    pub fn load_profile_password(&mut self, idx: &str) -> Result<bool> {
+        match crate::db::db_profile_vault(idx)? {
+            Some(vault) => {
+                self.pass = fetch(&vault)?;
+                self.set_source(&["pass"], "vault");
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Store the current password in the vault for a saved profile
+    pub fn save_profile_password(&self, idx: &str) -> Result<()> {
+        store(idx, &self.pass)?;
+        crate::db::db_set_profile_vault(idx, idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_secret_round_trips() {
+        let (key, _) = new_key("passphrase").unwrap();
+        let (nonce, secret) = encrypt(&key, b"root").unwrap();
+        assert_ne!(secret, b"root");
+        assert_eq!(decrypt(&key, &nonce, &secret).unwrap(), b"root");
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let (key, stored) = new_key("passphrase").unwrap();
+        assert_eq!(check_passphrase("passphrase", &stored).unwrap(), key);
+        let err = check_passphrase("other", &stored).unwrap_err();
+        assert_eq!(err.to_string(), "Wrong vault passphrase");
+    }
+
+    #[test]
+    fn tampered_entries_fail_to_decrypt() {
+        let (key, _) = new_key("passphrase").unwrap();
+        let (nonce, secret) = encrypt(&key, b"root").unwrap();
+
+        let mut tampered = secret.clone();
+        tampered[0] ^= 1;
+        assert!(decrypt(&key, &nonce, &tampered).is_err());
+
+        let mut moved = nonce.clone();
+        moved[0] ^= 1;
+        assert!(decrypt(&key, &moved, &secret).is_err());
+        assert!(decrypt(&key, &nonce[..11], &secret).is_err());
+    }
+
+    #[test]
+    fn rotation_reencrypts_every_entry() {
+        let (old, _) = new_key("old").unwrap();
+        let (key, _) = new_key("new").unwrap();
+        let entries = ["dev", "prod"]
+            .iter()
+            .map(|idx| {
+                let (nonce, secret) = encrypt(&old, format!("pass-{}", idx).as_bytes()).unwrap();
+                VaultEntry { idx: idx.to_string(), nonce, secret }
+            })
+            .collect();
+
+        let rotated = reencrypt(&old, &key, entries).unwrap();
+        assert_eq!(rotated.len(), 2);
+        for entry in rotated {
+            assert!(decrypt(&old, &entry.nonce, &entry.secret).is_err());
+            let plain = decrypt(&key, &entry.nonce, &entry.secret).unwrap();
+            assert_eq!(plain, format!("pass-{}", entry.idx).as_bytes());
+        }
+    }
+}